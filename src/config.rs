@@ -1,3 +1,4 @@
+use crate::settings::{ChatAppSettings, SETTINGS_FILE_NAME};
 use anyhow::{Context, Result};
 use config::Config;
 use serde::{Deserialize, Serialize};
@@ -12,6 +13,7 @@ pub struct ChatAppConfig {
     origin_url_str: String,
     pub token: Option<String>,
     pub cache_path: Option<PathBuf>,
+    pub settings: ChatAppSettings,
     #[serde(skip)]
     pub cdn_url: Option<Url>,
     #[serde(skip)]
@@ -43,6 +45,7 @@ impl ChatAppConfig {
             websocket_url_str: websocket_url.to_string(),
             cdn_url: Some(cdn_url),
             cache_path,
+            settings: ChatAppSettings::default(),
             websocket_url: Some(websocket_url),
             websocket_config: WebSocketConfig::default(),
             token,
@@ -57,12 +60,25 @@ impl ChatAppConfig {
                     .required(true)
                     .format(config::FileFormat::Toml),
             )
+            .add_source(
+                config::File::with_name(SETTINGS_FILE_NAME)
+                    .required(false)
+                    .format(config::FileFormat::Toml),
+            )
             .build()
             .expect("Failed to load config");
 
         ChatAppConfig::try_from(config).expect("Failed to load config")
     }
 
+    /// The directory containing the cache, where other app data can be kept too.
+    pub fn get_cache_dir(&self) -> Option<PathBuf> {
+        self.cache_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(PathBuf::from)
+    }
+
     pub fn get_cdn_url(&self) -> Url {
         if let Some(cdn_url) = &self.cdn_url {
             cdn_url.clone()
@@ -130,12 +146,15 @@ impl TryFrom<Config> for ChatAppConfig {
 
         let token: Option<String> = config.get("dgg.token").unwrap();
 
-        Ok(ChatAppConfig::new(
-            origin_url,
-            cdn_url,
-            websocket_url,
-            cache_path,
-            token,
-        ))
+        let mut settings = config
+            .try_deserialize::<ChatAppSettings>()
+            .context("Failed to load settings")?
+            .with_path(PathBuf::from(format!("{}.toml", SETTINGS_FILE_NAME)));
+        settings.filters.compile();
+
+        let mut app_config =
+            ChatAppConfig::new(origin_url, cdn_url, websocket_url, cache_path, token);
+        app_config.settings = settings;
+        Ok(app_config)
     }
 }
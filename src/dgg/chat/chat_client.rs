@@ -1,9 +1,14 @@
 use crate::config::ChatAppConfig;
-use crate::dgg::models::event::{ChatMessageData, Event, EventData};
+use crate::dgg::models::event::{
+    BanRequestData, ChatMessageData, Event, EventData, ModerationData, WhisperData, EVENT_BAN,
+    EVENT_MUTE, EVENT_SUB_ONLY, EVENT_UNBAN, EVENT_UNMUTE, EVENT_WHISPER,
+};
 use crate::dgg::utilities::cdn::CdnClient;
 use anyhow::{anyhow, bail, Context, Result};
 use futures_util::stream::FusedStream;
 use futures_util::{SinkExt, TryStreamExt};
+use serde::Serialize;
+use std::time::Duration;
 
 use tokio::net::TcpStream;
 
@@ -11,6 +16,16 @@ use tokio_tungstenite::tungstenite::handshake::client::{generate_key, Request};
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
 
+/// The longest chat message or whisper accepted by dgg, in characters.
+pub const MAX_MESSAGE_LENGTH: usize = 512;
+
+/// A mute or ban duration in nanoseconds, as the server takes it, or an error if it's too long
+/// to send.
+pub fn duration_nanos(duration: Duration) -> Result<u64> {
+    u64::try_from(duration.as_nanos())
+        .with_context(|| format!("The duration of {}s is too long", duration.as_secs()))
+}
+
 #[derive(Debug)]
 pub enum WebSocketMessage {
     Event(Event),
@@ -48,6 +63,74 @@ impl ChatClient {
         Ok(())
     }
 
+    pub async fn send_whisper(&mut self, nick: String, message: String) -> Result<()> {
+        self.send(
+            EVENT_WHISPER,
+            &WhisperData {
+                nick,
+                data: message,
+            },
+        )
+        .await
+    }
+
+    pub async fn mute(&mut self, nick: String, duration: Option<Duration>) -> Result<()> {
+        self.send(
+            EVENT_MUTE,
+            &ModerationData {
+                data: nick,
+                duration: duration.map(duration_nanos).transpose()?,
+            },
+        )
+        .await
+    }
+
+    pub async fn unmute(&mut self, nick: String) -> Result<()> {
+        self.send(
+            EVENT_UNMUTE,
+            &ModerationData {
+                data: nick,
+                duration: None,
+            },
+        )
+        .await
+    }
+
+    pub async fn ban(&mut self, ban: BanRequestData) -> Result<()> {
+        self.send(EVENT_BAN, &ban).await
+    }
+
+    pub async fn unban(&mut self, nick: String) -> Result<()> {
+        self.send(
+            EVENT_UNBAN,
+            &ModerationData {
+                data: nick,
+                duration: None,
+            },
+        )
+        .await
+    }
+
+    pub async fn set_sub_only(&mut self, enabled: bool) -> Result<()> {
+        self.send(
+            EVENT_SUB_ONLY,
+            &ModerationData {
+                data: if enabled { "on" } else { "off" }.to_string(),
+                duration: None,
+            },
+        )
+        .await
+    }
+
+    async fn send<T: Serialize>(&mut self, event_type: &str, data: &T) -> Result<()> {
+        let msg_str = format!("{} {}", event_type, serde_json::to_string(data)?);
+
+        let ws = self.ws.as_mut().context("Not connected")?;
+        debug!("Sending: {}", msg_str);
+        ws.send(Message::Text(msg_str)).await?;
+        Ok(())
+    }
+
     pub async fn connect(&mut self) -> Result<()> {
         info!("Connecting to {}", self.config.get_websocket_url());
         let ws = self.create_websocket_stream().await?;
@@ -103,7 +186,7 @@ impl ChatClient {
         let origin_url = self.config.get_origin_url();
         let websocket_url = self.config.get_websocket_url();
 
-        let request = Request::builder()
+        let mut request = Request::builder()
             .uri(websocket_url.as_str())
             .method("GET")
             .header("Host", websocket_url.host_str().unwrap())
@@ -112,12 +195,13 @@ impl ChatClient {
             .header("Sec-WebSocket-Version", "13")
             .header("Sec-WebSocket-Key", generate_key())
             .header("Origin", origin_url.as_str())
-            .header("User-Agent", "KogasaPls/dgg")
-            .header(
-                "Cookie",
-                format!("authtoken={}", self.config.token.as_ref().unwrap()),
-            )
-            .body(())?;
+            .header("User-Agent", "KogasaPls/dgg");
+
+        if let Some(token) = self.config.token.as_ref() {
+            request = request.header("Cookie", format!("authtoken={}", token));
+        }
+
+        let request = request.body(())?;
 
         let (stream, _) = tokio_tungstenite::connect_async_tls_with_config(
             request,
@@ -150,8 +234,8 @@ mod tests {
     fn get_test_config() -> ChatAppConfig {
         ChatAppConfig::new(
             Url::parse(&format!("https://{}/", MOCK_SERVER_ADDRESS)).unwrap(),
-            Url::parse(&format!("ws://{}/ws", MOCK_SERVER_ADDRESS)).unwrap(),
             Url::parse(&format!("https://{}/cdn", MOCK_SERVER_ADDRESS)).unwrap(),
+            Url::parse(&format!("ws://{}/ws", MOCK_SERVER_ADDRESS)).unwrap(),
             None,
             None,
         )
@@ -48,7 +48,7 @@ impl MockChatServer {
         let listener = TcpListener::bind(&self.address)
             .await
             .expect("Can't listen");
-        info!("Listening on: {}", &self.address);
+        info!("Listening on: {}", self.address);
 
         self.is_started = true;
 
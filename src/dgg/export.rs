@@ -0,0 +1,346 @@
+use crate::dgg::filter::{FilterAction, FilterInput, FilterSettings};
+use crate::dgg::history::{HistoryQuery, HistoryStore, SCROLLBACK_EVENT_TYPES};
+use crate::dgg::models::emote::split_emote_modifiers;
+use crate::dgg::models::event::Event;
+use crate::dgg::models::image::Image;
+use crate::settings::IgnoreSettings;
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use linkify::{LinkFinder, LinkKind};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// The timestamp format of OverRustle logs.
+pub const TEXT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S UTC";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    /// OverRustle-style lines like `[2023-06-25 05:34:02 UTC] nick: message`.
+    Text,
+    /// One JSON object per event, with its type in a `type` field.
+    Ndjson,
+    /// A standalone web page, with the emote images embedded.
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] =
+        [ExportFormat::Text, ExportFormat::Ndjson, ExportFormat::Html];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Plain text",
+            ExportFormat::Ndjson => "NDJSON",
+            ExportFormat::Html => "HTML",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// What to export from the history, and where to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportRequest {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+    /// Messages hidden by these filters or ignored are left out.
+    pub filters: FilterSettings,
+    pub ignore: IgnoreSettings,
+    /// Emote images by prefix, which are embedded in HTML exports and used to tell which
+    /// messages are emote-only.
+    pub emotes: HashMap<String, Image>,
+}
+
+/// Exports the requested events from the history to a file, which is written on tokio's
+/// blocking thread pool. Returns the number of events written.
+pub async fn export(history: &HistoryStore, request: ExportRequest) -> Result<usize> {
+    let events = history
+        .query(HistoryQuery {
+            after: request.after,
+            before: request.before,
+            event_types: SCROLLBACK_EVENT_TYPES.map(String::from).to_vec(),
+            ..Default::default()
+        })
+        .await?;
+
+    tokio::task::spawn_blocking(move || {
+        let file = File::create(&request.path)
+            .with_context(|| format!("Failed to create {}", request.path.display()))?;
+        let mut writer = BufWriter::new(file);
+        let count = write_export(&mut writer, &events, &request)?;
+        writer.flush()?;
+        Ok(count)
+    })
+    .await?
+}
+
+/// Writes the events that pass the request's filters in its format. Plain text and HTML only
+/// include chat messages, and HTML broadcasts too. Returns the number of events written.
+pub fn write_export<W: Write>(
+    writer: &mut W,
+    events: &[(DateTime<Utc>, Event)],
+    request: &ExportRequest,
+) -> Result<usize> {
+    let mut filters = request.filters.clone();
+    filters.compile();
+
+    let events: Vec<_> = events
+        .iter()
+        .filter(|(_, event)| !is_hidden(event, &filters, request))
+        .collect();
+
+    let mut count = 0;
+    if request.format == ExportFormat::Html {
+        writeln!(writer, "{}", HTML_HEADER)?;
+    }
+    for (timestamp, event) in events {
+        let is_written = match request.format {
+            ExportFormat::Text => write_text(writer, timestamp, event)?,
+            ExportFormat::Ndjson => write_json(writer, event)?,
+            ExportFormat::Html => write_html(writer, timestamp, event, &request.emotes)?,
+        };
+        if is_written {
+            count += 1;
+        }
+    }
+    if request.format == ExportFormat::Html {
+        writeln!(writer, "{}", HTML_FOOTER)?;
+    }
+    Ok(count)
+}
+
+fn is_hidden(event: &Event, filters: &FilterSettings, request: &ExportRequest) -> bool {
+    let Event::ChatMessage(message) = event else {
+        return false;
+    };
+    let Some(user) = message.base.user.as_ref() else {
+        return false;
+    };
+
+    let text = message.data.data.as_str();
+    if request.ignore.hides(&user.nick, text) {
+        return true;
+    }
+
+    let mut link_finder = LinkFinder::new();
+    link_finder.kinds(&[LinkKind::Url]);
+    let mut words = text.split_whitespace().peekable();
+    let is_emote_only = words.peek().is_some()
+        && words.all(|word| {
+            let prefix = split_emote_modifiers(word).map_or(word, |(prefix, _)| prefix);
+            request.emotes.contains_key(prefix)
+        });
+
+    let action = filters.evaluate(&FilterInput {
+        nick: &user.nick,
+        features: &user.features,
+        text,
+        has_link: link_finder.links(text).next().is_some(),
+        is_emote_only,
+    });
+    action == Some(FilterAction::Hide)
+}
+
+fn write_text<W: Write>(writer: &mut W, timestamp: &DateTime<Utc>, event: &Event) -> Result<bool> {
+    let Event::ChatMessage(message) = event else {
+        return Ok(false);
+    };
+    let nick = message.base.nick().unwrap_or_default();
+
+    writeln!(
+        writer,
+        "[{}] {}: {}",
+        timestamp.format(TEXT_TIMESTAMP_FORMAT),
+        nick,
+        message.data.data
+    )?;
+    Ok(true)
+}
+
+fn write_json<W: Write>(writer: &mut W, event: &Event) -> Result<bool> {
+    let raw = String::try_from(event.clone())?;
+    let (event_type, _) = raw
+        .split_once(' ')
+        .context("Serialized event has no type")?;
+
+    let mut json = serde_json::to_value(event)?;
+    if let Value::Object(object) = &mut json {
+        object.insert("type".to_string(), Value::String(event_type.to_string()));
+    }
+    writeln!(writer, "{}", json)?;
+    Ok(true)
+}
+
+fn write_html<W: Write>(
+    writer: &mut W,
+    timestamp: &DateTime<Utc>,
+    event: &Event,
+    emotes: &HashMap<String, Image>,
+) -> Result<bool> {
+    let (class, nick, text) = match event {
+        Event::ChatMessage(message) => ("msg", message.base.nick(), &message.data.data),
+        Event::Broadcast(broadcast) => ("msg broadcast", None, &broadcast.data.data),
+        _ => return Ok(false),
+    };
+
+    write!(
+        writer,
+        r#"<div class="{}"><time datetime="{}">{}</time> "#,
+        class,
+        timestamp.to_rfc3339(),
+        timestamp.format("%H:%M:%S")
+    )?;
+    if let Some(nick) = nick {
+        write!(
+            writer,
+            r#"<span class="nick">{}</span>: "#,
+            escape_html(nick)
+        )?;
+    }
+
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| {
+            let prefix = split_emote_modifiers(word).map_or(word, |(prefix, _)| prefix);
+            match emotes.get(prefix).and_then(emote_data_uri) {
+                Some(uri) => format!(
+                    r#"<img class="emote" src="{}" alt="{}" title="{}">"#,
+                    uri,
+                    escape_html(word),
+                    escape_html(word)
+                ),
+                None => escape_html(word),
+            }
+        })
+        .collect();
+    writeln!(writer, "{}</div>", words.join(" "))?;
+    Ok(true)
+}
+
+fn emote_data_uri(image: &Image) -> Option<String> {
+    let bytes = image.bytes.as_ref()?;
+    Some(format!(
+        "data:{};base64,{}",
+        image.mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Destiny.gg chat log</title>
+<style>
+body { background: #111; color: #ddd; font-family: sans-serif; font-size: 14px; }
+.msg { padding: 2px 4px; }
+.msg time { color: #777; }
+.nick { font-weight: bold; }
+.broadcast { background: #3a3220; color: #edc35a; }
+.emote { height: 2em; vertical-align: middle; }
+</style>
+</head>
+<body>"#;
+
+const HTML_FOOTER: &str = "</body>\n</html>";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dgg::filter::FilterRule;
+
+    fn request(format: ExportFormat) -> ExportRequest {
+        ExportRequest {
+            path: PathBuf::new(),
+            format,
+            after: None,
+            before: None,
+            filters: FilterSettings::default(),
+            ignore: IgnoreSettings::default(),
+            emotes: HashMap::new(),
+        }
+    }
+
+    fn events() -> Vec<(DateTime<Utc>, Event)> {
+        let message = |nick: &str, text: &str| {
+            Event::try_from(
+                format!(
+                    r#"MSG {{"id":1,"nick":"{}","features":[],"createdDate":"2021-11-17T20:20:01Z","timestamp":1687671242596,"data":"{}"}}"#,
+                    nick, text
+                )
+                .as_str(),
+            )
+            .unwrap()
+        };
+        let timestamp = DateTime::parse_from_rfc3339("2023-06-25T05:34:02Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        vec![
+            (timestamp, message("Bob", "hello <b>")),
+            (timestamp, message("Spammer", "buy now")),
+        ]
+    }
+
+    fn export_string(request: &ExportRequest) -> String {
+        let mut output = Vec::new();
+        write_export(&mut output, &events(), request).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn exports_overrustle_style_text_without_hidden_messages() {
+        let mut request = request(ExportFormat::Text);
+        request.ignore.ignore("spammer");
+
+        assert_eq!(
+            export_string(&request),
+            "[2023-06-25 05:34:02 UTC] Bob: hello <b>\n"
+        );
+    }
+
+    #[test]
+    fn exports_typed_json_and_escaped_html() {
+        let mut request = request(ExportFormat::Ndjson);
+        let mut rule = FilterRule::new("spam".to_string(), FilterAction::Hide);
+        rule.text = Some("buy".to_string());
+        request.filters.rules.push(rule);
+
+        let json = export_string(&request);
+        assert_eq!(json.lines().count(), 1);
+        let value: Value = serde_json::from_str(json.lines().next().unwrap()).unwrap();
+        assert_eq!(value["type"], "MSG");
+        assert_eq!(value["nick"], "Bob");
+
+        request.format = ExportFormat::Html;
+        let html = export_string(&request);
+        assert!(html.contains("hello &lt;b&gt;</div>"));
+        assert!(!html.contains("buy now"));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::dgg::models::flair::FlairKind;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// What to do with a message matched by a [FilterRule].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    #[default]
+    Hide,
+    Collapse,
+    Dim,
+    Highlight,
+}
+
+impl FilterAction {
+    pub const ALL: [FilterAction; 4] = [
+        FilterAction::Hide,
+        FilterAction::Collapse,
+        FilterAction::Dim,
+        FilterAction::Highlight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterAction::Hide => "Hide",
+            FilterAction::Collapse => "Collapse",
+            FilterAction::Dim => "Dim",
+            FilterAction::Highlight => "Highlight",
+        }
+    }
+}
+
+/// The parts of a chat message that [FilterRule]s can match on.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterInput<'a> {
+    pub nick: &'a str,
+    pub features: &'a [String],
+    pub text: &'a str,
+    pub has_link: bool,
+    pub is_emote_only: bool,
+}
+
+/// A rule matching messages on any combination of its conditions, all of which have to hold.
+/// A rule without any conditions never matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterRule {
+    pub name: String,
+    pub enabled: bool,
+    pub action: FilterAction,
+    /// A regex matched against the message text.
+    pub text: Option<String>,
+    /// A nick, compared case-insensitively.
+    pub nick: Option<String>,
+    /// A flair name as it appears in the user's features, e.g. `moderator` or `flair13`.
+    pub flair: Option<String>,
+    pub has_link: Option<bool>,
+    pub emote_only: Option<bool>,
+    #[serde(skip)]
+    regex: Option<Regex>,
+}
+
+/// Rules are equal if they have the same conditions, regardless of whether their pattern has
+/// been compiled.
+impl PartialEq for FilterRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.enabled == other.enabled
+            && self.action == other.action
+            && self.text == other.text
+            && self.nick == other.nick
+            && self.flair == other.flair
+            && self.has_link == other.has_link
+            && self.emote_only == other.emote_only
+    }
+}
+
+impl Eq for FilterRule {}
+
+impl Default for FilterRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            action: FilterAction::default(),
+            text: None,
+            nick: None,
+            flair: None,
+            has_link: None,
+            emote_only: None,
+            regex: None,
+        }
+    }
+}
+
+impl FilterRule {
+    pub fn new(name: String, action: FilterAction) -> Self {
+        Self {
+            name,
+            action,
+            ..Default::default()
+        }
+    }
+
+    /// Compiles the `text` pattern, which has to be done again whenever it changes.
+    pub fn compile(&mut self) -> Result<(), regex::Error> {
+        self.regex = None;
+        if let Some(pattern) = self.text.as_ref().filter(|p| !p.is_empty()) {
+            self.regex = Some(Regex::new(pattern)?);
+        }
+        Ok(())
+    }
+
+    /// Whether `text` is empty or has been compiled successfully.
+    pub fn has_valid_pattern(&self) -> bool {
+        match self.text.as_ref().filter(|p| !p.is_empty()) {
+            Some(pattern) => self.regex.as_ref().is_some_and(|r| r.as_str() == pattern),
+            None => true,
+        }
+    }
+
+    fn has_conditions(&self) -> bool {
+        self.text.as_ref().is_some_and(|t| !t.is_empty())
+            || self.nick.as_ref().is_some_and(|n| !n.is_empty())
+            || self.flair.as_ref().is_some_and(|f| !f.is_empty())
+            || self.has_link.is_some()
+            || self.emote_only.is_some()
+    }
+
+    pub fn matches(&self, input: &FilterInput<'_>) -> bool {
+        if !self.enabled || !self.has_conditions() {
+            return false;
+        }
+
+        if !self.has_valid_pattern() {
+            return false;
+        }
+
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(input.text) {
+                return false;
+            }
+        }
+
+        if let Some(nick) = self.nick.as_ref().filter(|n| !n.is_empty()) {
+            if !nick.eq_ignore_ascii_case(input.nick) {
+                return false;
+            }
+        }
+
+        if let Some(flair) = self.flair.as_ref().filter(|f| !f.is_empty()) {
+            let kind = FlairKind::from(flair.as_str());
+            if !input
+                .features
+                .iter()
+                .any(|f| FlairKind::from(f.as_str()) == kind)
+            {
+                return false;
+            }
+        }
+
+        if self.has_link.is_some_and(|l| l != input.has_link) {
+            return false;
+        }
+
+        self.emote_only.is_none_or(|e| e == input.is_emote_only)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterSettings {
+    pub rules: Vec<FilterRule>,
+}
+
+impl FilterSettings {
+    /// Compiles every rule, logging the ones that are invalid.
+    pub fn compile(&mut self) {
+        for rule in self.rules.iter_mut() {
+            if let Err(e) = rule.compile() {
+                warn!("Invalid pattern in filter rule {:?}: {}", rule.name, e);
+            }
+        }
+    }
+
+    /// The action of the first rule matching the message, if any.
+    pub fn evaluate(&self, input: &FilterInput<'_>) -> Option<FilterAction> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(input))
+            .map(|rule| rule.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input<'a>(nick: &'a str, features: &'a [String], text: &'a str) -> FilterInput<'a> {
+        FilterInput {
+            nick,
+            features,
+            text,
+            has_link: false,
+            is_emote_only: false,
+        }
+    }
+
+    fn rule(action: FilterAction) -> FilterRule {
+        FilterRule {
+            action,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rule_without_conditions_never_matches() {
+        let settings = FilterSettings {
+            rules: vec![rule(FilterAction::Hide)],
+        };
+
+        assert_eq!(settings.evaluate(&input("nick", &[], "text")), None);
+    }
+
+    #[test]
+    fn all_conditions_must_match() {
+        let mut rule = FilterRule {
+            text: Some("(?i)^gm$".to_string()),
+            flair: Some("flair13".to_string()),
+            ..rule(FilterAction::Dim)
+        };
+        rule.compile().unwrap();
+        let settings = FilterSettings { rules: vec![rule] };
+        let features = vec!["subscriber".to_string(), "flair13".to_string()];
+
+        assert_eq!(
+            settings.evaluate(&input("nick", &features, "GM")),
+            Some(FilterAction::Dim)
+        );
+        assert_eq!(settings.evaluate(&input("nick", &[], "GM")), None);
+        assert_eq!(settings.evaluate(&input("nick", &features, "gn")), None);
+    }
+
+    #[test]
+    fn partly_typed_flairs_match_literally() {
+        let features = vec!["flair13".to_string(), "flairx".to_string()];
+        for (flair, expected) in [("flair", None), ("flairx", Some(FilterAction::Dim))] {
+            let settings = FilterSettings {
+                rules: vec![FilterRule {
+                    flair: Some(flair.to_string()),
+                    ..rule(FilterAction::Dim)
+                }],
+            };
+            assert_eq!(settings.evaluate(&input("nick", &features, "")), expected);
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let settings = FilterSettings {
+            rules: vec![
+                FilterRule {
+                    nick: Some("Destiny".to_string()),
+                    ..rule(FilterAction::Highlight)
+                },
+                FilterRule {
+                    has_link: Some(true),
+                    ..rule(FilterAction::Collapse)
+                },
+            ],
+        };
+        let mut message = input("destiny", &[], "https://destiny.gg");
+        message.has_link = true;
+
+        assert_eq!(settings.evaluate(&message), Some(FilterAction::Highlight));
+
+        message.nick = "someone";
+        assert_eq!(settings.evaluate(&message), Some(FilterAction::Collapse));
+    }
+
+    #[test]
+    fn uncompiled_pattern_does_not_match() {
+        let settings = FilterSettings {
+            rules: vec![FilterRule {
+                text: Some("hello".to_string()),
+                ..rule(FilterAction::Hide)
+            }],
+        };
+
+        assert_eq!(settings.evaluate(&input("nick", &[], "hello")), None);
+    }
+}
@@ -0,0 +1,370 @@
+use crate::dgg::models::event::{
+    Event, EVENT_BAN, EVENT_BROADCAST, EVENT_CHAT_MESSAGE, EVENT_MUTE, EVENT_SUB_ONLY, EVENT_UNBAN,
+    EVENT_UNMUTE,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The event types shown in the chat, which are replayed as scrollback on startup.
+pub const SCROLLBACK_EVENT_TYPES: [&str; 7] = [
+    EVENT_CHAT_MESSAGE,
+    EVENT_BROADCAST,
+    EVENT_MUTE,
+    EVENT_UNMUTE,
+    EVENT_BAN,
+    EVENT_UNBAN,
+    EVENT_SUB_ONLY,
+];
+
+/// Whether the event is worth keeping in the history. Presence updates like joins and quits
+/// are left out, since they arrive constantly and aren't shown in the chat.
+pub fn is_stored(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::UserJoined(_) | Event::UserQuit(_) | Event::ServedConnections(_) | Event::Unknown(_)
+    )
+}
+
+/// Which stored events to return from [HistoryStore::query]. Every condition that is set has to
+/// hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryQuery {
+    /// The nick of the sender, compared case-insensitively.
+    pub nick: Option<String>,
+    /// Text that the event's `data` has to contain, ignoring case.
+    pub keyword: Option<String>,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+    /// Event types like [EVENT_CHAT_MESSAGE], or all types if empty.
+    pub event_types: Vec<String>,
+    /// Return only the latest matching events.
+    pub limit: Option<usize>,
+}
+
+impl HistoryQuery {
+    /// The latest `limit` events that are shown in the chat.
+    pub fn scrollback(limit: usize) -> Self {
+        Self {
+            event_types: SCROLLBACK_EVENT_TYPES.map(String::from).to_vec(),
+            limit: Some(limit),
+            ..Default::default()
+        }
+    }
+}
+
+/// A log of received events in an SQLite database, indexed by time, nick and event type.
+///
+/// The database is accessed on tokio's blocking thread pool, so the store can be cloned and
+/// used from async tasks.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl HistoryStore {
+    /// Opens the database at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open history at {}", path.display()))?;
+        Self::with_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY,
+                timestamp INTEGER NOT NULL,
+                nick TEXT,
+                event_type TEXT NOT NULL,
+                data TEXT,
+                raw TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS events_timestamp ON events (timestamp);
+            CREATE INDEX IF NOT EXISTS events_nick ON events (nick COLLATE NOCASE);
+            CREATE INDEX IF NOT EXISTS events_event_type ON events (event_type);",
+        )?;
+
+        // Storing the same event again, e.g. by importing a log twice, is ignored. Databases from
+        // before this may already hold such copies, which have to go first.
+        let has_unique_index: bool = connection.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'events_unique'
+            )",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_unique_index {
+            connection.execute_batch(
+                "DELETE FROM events WHERE id NOT IN (
+                    SELECT MIN(id) FROM events GROUP BY timestamp, nick, event_type, data
+                );
+                CREATE UNIQUE INDEX events_unique ON events (timestamp, nick, event_type, data);",
+            )?;
+        }
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn with_connection_blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow!("History database lock was poisoned"))?;
+            f(&mut connection)
+        })
+        .await?
+    }
+
+    /// Stores an event, using the current time if it has no timestamp.
+    pub async fn insert(&self, event: Event) -> Result<()> {
+        self.insert_all(vec![event]).await
+    }
+
+    /// Stores several events in one transaction, which is much faster than one by one. Events
+    /// that are already stored are skipped.
+    pub async fn insert_all(&self, events: Vec<Event>) -> Result<()> {
+        let rows = events
+            .into_iter()
+            .map(StoredEvent::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        self.with_connection_blocking(move |connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(
+                    "INSERT OR IGNORE INTO events (timestamp, nick, event_type, data, raw)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?;
+                for row in rows {
+                    statement.execute(params![
+                        row.timestamp.timestamp_millis(),
+                        row.nick,
+                        row.event_type,
+                        row.data,
+                        row.raw
+                    ])?;
+                }
+            }
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// The matching events with their timestamps, oldest first.
+    pub async fn query(&self, query: HistoryQuery) -> Result<Vec<(DateTime<Utc>, Event)>> {
+        self.with_connection_blocking(move |connection| {
+            let mut conditions = Vec::new();
+            let mut values: Vec<SqlValue> = Vec::new();
+
+            if let Some(nick) = query.nick {
+                conditions.push("nick = ? COLLATE NOCASE".to_string());
+                values.push(nick.into());
+            }
+            if let Some(keyword) = query.keyword {
+                conditions.push("instr(lower(data), lower(?)) > 0".to_string());
+                values.push(keyword.into());
+            }
+            if let Some(after) = query.after {
+                conditions.push("timestamp >= ?".to_string());
+                values.push(after.timestamp_millis().into());
+            }
+            if let Some(before) = query.before {
+                conditions.push("timestamp <= ?".to_string());
+                values.push(before.timestamp_millis().into());
+            }
+            if !query.event_types.is_empty() {
+                let placeholders = vec!["?"; query.event_types.len()].join(", ");
+                conditions.push(format!("event_type IN ({})", placeholders));
+                values.extend(query.event_types.into_iter().map(SqlValue::from));
+            }
+
+            let mut sql = "SELECT timestamp, raw FROM events".to_string();
+            if !conditions.is_empty() {
+                sql.push_str(" WHERE ");
+                sql.push_str(&conditions.join(" AND "));
+            }
+            // Take the latest events, and reverse them below.
+            sql.push_str(" ORDER BY timestamp DESC, id DESC");
+            if let Some(limit) = query.limit {
+                sql.push_str(&format!(" LIMIT {}", limit));
+            }
+
+            let mut statement = connection.prepare(&sql)?;
+            let rows = statement.query_map(params_from_iter(values), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?;
+
+            let mut events = Vec::new();
+            for row in rows {
+                let (timestamp, raw) = row?;
+                let timestamp = Utc
+                    .timestamp_millis_opt(timestamp)
+                    .single()
+                    .context("Invalid timestamp in history")?;
+                match Event::try_from(raw.as_str()) {
+                    Ok(event) => events.push((timestamp, event)),
+                    Err(e) => warn!("Skipping unreadable event in history: {:?}", e),
+                }
+            }
+            events.reverse();
+            Ok(events)
+        })
+        .await
+    }
+}
+
+/// The columns an event is stored with.
+struct StoredEvent {
+    timestamp: DateTime<Utc>,
+    nick: Option<String>,
+    event_type: String,
+    data: Option<String>,
+    raw: String,
+}
+
+impl TryFrom<Event> for StoredEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: Event) -> Result<Self> {
+        let json = serde_json::to_value(&event)?;
+        let field = |name: &str| json.get(name).and_then(Value::as_str).map(String::from);
+        let timestamp = json
+            .get("timestamp")
+            .and_then(Value::as_i64)
+            .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+            .unwrap_or_else(Utc::now);
+        let nick = field("nick");
+        let data = field("data");
+
+        let raw = String::try_from(event)?;
+        let event_type = raw
+            .split_once(' ')
+            .map(|(event_type, _)| event_type.to_string())
+            .context("Serialized event has no type")?;
+
+        Ok(Self {
+            timestamp,
+            nick,
+            event_type,
+            data,
+            raw,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dgg::models::event::EVENT_WHISPER;
+
+    fn message(nick: &str, timestamp: i64, text: &str) -> Event {
+        Event::try_from(
+            format!(
+                r#"MSG {{"id":1,"nick":"{}","features":[],"createdDate":"2021-11-17T20:20:01Z","timestamp":{},"data":"{}"}}"#,
+                nick, timestamp, text
+            )
+            .as_str(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn skips_presence_events() -> Result<()> {
+        assert!(is_stored(&message("Bob", 1_000, "hi")));
+        let quit = Event::try_from(include_resource!("test_samples", "events", "QUIT"))?;
+        assert!(!is_stored(&quit));
+        let names = Event::try_from(include_resource!("test_samples", "events", "NAMES"))?;
+        assert!(!is_stored(&names));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stores_each_event_once() -> Result<()> {
+        let store = HistoryStore::open_in_memory()?;
+        let events = vec![message("Bob", 1_000, "hi"), message("Bob", 2_000, "hi")];
+        store.insert_all(events.clone()).await?;
+        store.insert_all(events).await?;
+        assert_eq!(store.query(HistoryQuery::default()).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn queries_by_nick_keyword_and_time() -> Result<()> {
+        let store = HistoryStore::open_in_memory()?;
+        store
+            .insert_all(vec![
+                message("Destiny", 1_000, "hello chat"),
+                message("Bob", 2_000, "Hello Destiny"),
+                message("destiny", 3_000, "goodbye"),
+            ])
+            .await?;
+        store
+            .insert(Event::try_from(
+                r#"PRIVMSG {"timestamp":4000,"nick":"Bob","data":"hello"}"#,
+            )?)
+            .await?;
+
+        let by_nick = HistoryQuery {
+            nick: Some("DESTINY".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.query(by_nick).await?.len(), 2);
+
+        let by_keyword = HistoryQuery {
+            keyword: Some("HELLO".to_string()),
+            event_types: vec![EVENT_CHAT_MESSAGE.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(store.query(by_keyword).await?.len(), 2);
+
+        let by_time = HistoryQuery {
+            after: Utc.timestamp_millis_opt(1_500).single(),
+            before: Utc.timestamp_millis_opt(3_500).single(),
+            ..Default::default()
+        };
+        assert_eq!(store.query(by_time).await?.len(), 2);
+
+        let whispers = HistoryQuery {
+            event_types: vec![EVENT_WHISPER.to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            store.query(whispers).await?.as_slice(),
+            [(_, Event::Whisper(_))]
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scrollback_is_the_latest_events_oldest_first() -> Result<()> {
+        let store = HistoryStore::open_in_memory()?;
+        store
+            .insert_all((0..5).map(|i| message("Bob", i * 1000, "hi")).collect())
+            .await?;
+
+        let events = store.query(HistoryQuery::scrollback(2)).await?;
+        let timestamps: Vec<_> = events.iter().map(|(t, _)| t.timestamp()).collect();
+        assert_eq!(timestamps, vec![3, 4]);
+        Ok(())
+    }
+}
@@ -1,3 +1,8 @@
 pub mod chat;
+pub mod export;
+pub mod filter;
+pub mod history;
 pub mod models;
+pub mod overrustle;
+pub mod search;
 pub mod utilities;
@@ -11,3 +11,64 @@ pub struct Emote {
     pub minimum_sub_tier: u8,
     pub image: Vec<Image>,
 }
+
+/// A modifier changing how an emote is shown, written after it like `PEPE:wide:flip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmoteModifier {
+    Mirror,
+    Flip,
+    Wide,
+    Rain,
+    Snow,
+    Rustle,
+    Worth,
+    Hyper,
+    Love,
+    Spin,
+}
+
+impl EmoteModifier {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let modifier = match name.to_ascii_lowercase().as_str() {
+            "mirror" => EmoteModifier::Mirror,
+            "flip" => EmoteModifier::Flip,
+            "wide" => EmoteModifier::Wide,
+            "rain" => EmoteModifier::Rain,
+            "snow" => EmoteModifier::Snow,
+            "rustle" => EmoteModifier::Rustle,
+            "worth" => EmoteModifier::Worth,
+            "hyper" => EmoteModifier::Hyper,
+            "love" => EmoteModifier::Love,
+            "spin" => EmoteModifier::Spin,
+            _ => return None,
+        };
+        Some(modifier)
+    }
+}
+
+/// Splits a word like `PEPE:wide:flip` into the emote prefix and its modifiers. Returns `None`
+/// if there are no modifiers, or any of them is unknown.
+pub fn split_emote_modifiers(word: &str) -> Option<(&str, Vec<EmoteModifier>)> {
+    let (prefix, modifiers) = word.split_once(':')?;
+    let modifiers = modifiers
+        .split(':')
+        .map(EmoteModifier::from_name)
+        .collect::<Option<Vec<_>>>()?;
+    Some((prefix, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_emote_modifiers() {
+        assert_eq!(
+            split_emote_modifiers("PEPE:wide:FLIP"),
+            Some(("PEPE", vec![EmoteModifier::Wide, EmoteModifier::Flip]))
+        );
+        assert_eq!(split_emote_modifiers("PEPE"), None);
+        assert_eq!(split_emote_modifiers("PEPE:dance"), None);
+        assert_eq!(split_emote_modifiers("PEPE:"), None);
+    }
+}
@@ -7,27 +7,27 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
-const EVENT_ME: &str = "ME";
-const EVENT_SERVED_CONNECTIONS: &str = "NAMES";
-const EVENT_USER_JOINED: &str = "JOIN";
-const EVENT_USER_QUIT: &str = "QUIT";
-const EVENT_BROADCAST: &str = "BROADCAST";
-const EVENT_CHAT_MESSAGE: &str = "MSG";
-const EVENT_WHISPER: &str = "PRIVMSG";
-const EVENT_WHISPER_SENT: &str = "PRIVMSGSENT";
-const EVENT_MUTE: &str = "MUTE";
-const EVENT_UNMUTE: &str = "UNMUTE";
-const EVENT_BAN: &str = "BAN";
-const EVENT_UNBAN: &str = "UNBAN";
-const EVENT_SUB_ONLY: &str = "SUBONLY";
-const EVENT_PIN: &str = "PIN";
-const EVENT_ERROR_MESSAGE: &str = "ERR";
-const EVENT_BEFORE_EVERY_MESSAGE: &str = "BEFORE_EVERY_MESSAGE";
-const EVENT_AFTER_EVERY_MESSAGE: &str = "AFTER_EVERY_MESSAGE";
-const EVENT_MENTION: &str = "MENTION";
-const EVENT_WEBSOCKET_ERROR: &str = "WS_ERROR";
-const EVENT_WEBSOCKET_CLOSE: &str = "WS_CLOSE";
-const EVENT_HANDLER_ERROR: &str = "HANDLER_ERROR";
+pub const EVENT_ME: &str = "ME";
+pub const EVENT_SERVED_CONNECTIONS: &str = "NAMES";
+pub const EVENT_USER_JOINED: &str = "JOIN";
+pub const EVENT_USER_QUIT: &str = "QUIT";
+pub const EVENT_BROADCAST: &str = "BROADCAST";
+pub const EVENT_CHAT_MESSAGE: &str = "MSG";
+pub const EVENT_WHISPER: &str = "PRIVMSG";
+pub const EVENT_WHISPER_SENT: &str = "PRIVMSGSENT";
+pub const EVENT_MUTE: &str = "MUTE";
+pub const EVENT_UNMUTE: &str = "UNMUTE";
+pub const EVENT_BAN: &str = "BAN";
+pub const EVENT_UNBAN: &str = "UNBAN";
+pub const EVENT_SUB_ONLY: &str = "SUBONLY";
+pub const EVENT_PIN: &str = "PIN";
+pub const EVENT_ERROR_MESSAGE: &str = "ERR";
+pub const EVENT_BEFORE_EVERY_MESSAGE: &str = "BEFORE_EVERY_MESSAGE";
+pub const EVENT_AFTER_EVERY_MESSAGE: &str = "AFTER_EVERY_MESSAGE";
+pub const EVENT_MENTION: &str = "MENTION";
+pub const EVENT_WEBSOCKET_ERROR: &str = "WS_ERROR";
+pub const EVENT_WEBSOCKET_CLOSE: &str = "WS_CLOSE";
+pub const EVENT_HANDLER_ERROR: &str = "HANDLER_ERROR";
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BaseEventData {
@@ -43,6 +43,17 @@ pub struct BaseEventData {
     pub timestamp: Option<DateTime<Utc>>,
 }
 
+impl BaseEventData {
+    /// The nick of the user that sent the event, which is only given as a bare `nick` field
+    /// (rather than a full [User]) for some events, such as whispers.
+    pub fn nick(&self) -> Option<&str> {
+        match &self.user {
+            Some(user) => Some(user.nick.as_str()),
+            None => self.extra.as_ref()?.get("nick")?.as_str(),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EventData<T> {
     #[serde(flatten)]
@@ -59,16 +70,16 @@ pub enum Event {
     ServedConnections(EventData<ServedConnectionsData>),
     UserJoined(BaseEventData),
     UserQuit(BaseEventData),
-    Broadcast(BaseEventData),
+    Broadcast(EventData<ChatMessageData>),
     ChatMessage(EventData<ChatMessageData>),
-    Whisper(BaseEventData),
+    Whisper(EventData<WhisperData>),
     WhisperSent(BaseEventData),
-    Mute(BaseEventData),
-    Unmute(BaseEventData),
-    Ban(BaseEventData),
-    Unban(BaseEventData),
-    SubOnly(BaseEventData),
-    Pin(BaseEventData),
+    Mute(EventData<ModerationData>),
+    Unmute(EventData<ModerationData>),
+    Ban(EventData<ModerationData>),
+    Unban(EventData<ModerationData>),
+    SubOnly(EventData<ModerationData>),
+    Pin(EventData<PinData>),
     ErrorMessage(BaseEventData),
     BeforeEveryMessage(BaseEventData),
     AfterEveryMessage(BaseEventData),
@@ -172,6 +183,52 @@ pub struct ChatMessageData {
     pub data: String,
 }
 
+/// A private message to or from `nick`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct WhisperData {
+    pub nick: String,
+    pub data: String,
+}
+
+/// A message pinned above the chat by a moderator. An empty `data` means the pin was removed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct PinData {
+    #[serde(default)]
+    pub uuid: String,
+    #[serde(default)]
+    pub data: String,
+}
+
+impl PinData {
+    pub fn is_unpin(&self) -> bool {
+        self.data.trim().is_empty()
+    }
+}
+
+/// The payload of moderation events, where `data` is the target user or, for sub-only mode,
+/// `"on"` or `"off"`.
+///
+/// `duration` is in seconds when received, but has to be sent in nanoseconds.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ModerationData {
+    pub data: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+}
+
+/// The payload of a ban request. `duration` is in nanoseconds.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct BanRequestData {
+    pub nick: String,
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    #[serde(rename = "ispermanent")]
+    pub is_permanent: bool,
+    #[serde(rename = "banip")]
+    pub ban_ip: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use crate::dgg::models::event::Event;
@@ -211,7 +268,28 @@ mod tests {
         let event = Event::try_from(include_resource!("test_samples", "events", "PIN"))?;
         debug!("{:?}", event);
 
-        assert!(matches!(event, Event::Pin(_)));
+        let Event::Pin(pin) = event else {
+            panic!("Expected a pin");
+        };
+        assert_eq!(pin.data.uuid, "e056fc8b-d91a-4ab4-b1ef-d63062680a54");
+        assert!(!pin.data.is_unpin());
+        assert!(pin.base.timestamp.is_some());
+        assert_eq!(pin.base.nick(), Some("Cake"));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_event_whisper() -> Result<()> {
+        let event = Event::try_from(
+            r#"PRIVMSG {"messageid":1,"timestamp":1687464272976,"nick":"Bob","data":"hi"}"#,
+        )?;
+
+        let Event::Whisper(whisper) = event else {
+            panic!("Expected a whisper");
+        };
+        assert_eq!(whisper.data.nick, "Bob");
+        assert_eq!(whisper.data.data, "hi");
+        assert!(whisper.base.timestamp.is_some());
         Ok(())
     }
 }
@@ -1,5 +1,4 @@
 use crate::dgg::models::image::Image;
-use anyhow::Context;
 use palette::rgb::Rgb;
 use serde::{Deserialize, Serialize};
 
@@ -48,14 +47,13 @@ pub enum FlairKind {
 }
 
 // c.f. https://cdn.destiny.gg/flairs/flairs.json
+// Names like `flairx`, which aren't followed by a number, are [FlairKind::Other].
 impl From<&str> for FlairKind {
     fn from(value: &str) -> Self {
-        match value.strip_prefix("flair").map(|value| {
-            value
-                .parse::<u64>()
-                .context("Expected flair to be followed by a number")
-                .unwrap()
-        }) {
+        match value
+            .strip_prefix("flair")
+            .and_then(|value| value.parse::<u64>().ok())
+        {
             Some(number) => match number {
                 1 => FlairKind::SubscriberTier2,
                 2 => FlairKind::Notable,
@@ -103,6 +101,20 @@ impl From<&str> for FlairKind {
     }
 }
 
+impl FlairKind {
+    /// The subscription tier this flair indicates, if any.
+    pub fn sub_tier(&self) -> Option<u8> {
+        match self {
+            FlairKind::Subscriber | FlairKind::SubscriberTier1 => Some(1),
+            FlairKind::SubscriberTier2 => Some(2),
+            FlairKind::SubscriberTier3 => Some(3),
+            FlairKind::SubscriberTier4 => Some(4),
+            FlairKind::SubscriberTier5 => Some(5),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Flair {
@@ -120,7 +132,6 @@ pub struct Flair {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::include_resource;
 
     #[test]
     fn test_flair_deserialization() {
@@ -129,4 +140,11 @@ mod tests {
 
         debug!("{:#?}", flairs);
     }
+
+    #[test]
+    fn test_sub_tier() {
+        assert_eq!(FlairKind::from("subscriber").sub_tier(), Some(1));
+        assert_eq!(FlairKind::from("flair8").sub_tier(), Some(4));
+        assert_eq!(FlairKind::from("moderator").sub_tier(), None);
+    }
 }
@@ -10,3 +10,76 @@ pub struct Image {
     #[serde(default)]
     pub bytes: Option<Vec<u8>>,
 }
+
+impl Image {
+    /// Picks the entry to draw at `height` pixels among those whose bytes are loaded: the
+    /// smallest one at least that high, or the highest one if there is none.
+    pub fn best_for_height(images: &[Image], height: f32) -> Option<&Image> {
+        let index = best_index(images, height, |i| i.bytes.is_some())?;
+        Some(&images[index])
+    }
+
+    /// Picks the entry to download for drawing at `height` pixels, by the same rule as
+    /// [Image::best_for_height] but among every entry.
+    pub fn best_for_height_mut(images: &mut [Image], height: f32) -> Option<&mut Image> {
+        let index = best_index(images, height, |_| true)?;
+        Some(&mut images[index])
+    }
+
+    /// The height of the entry with the lowest resolution, which is the size it is drawn at.
+    pub fn native_height(images: &[Image]) -> Option<u16> {
+        images.iter().map(|i| i.height).min()
+    }
+}
+
+/// The index of the smallest candidate at least `height` high, or of the highest candidate.
+fn best_index(
+    images: &[Image],
+    height: f32,
+    is_candidate: impl Fn(&Image) -> bool,
+) -> Option<usize> {
+    let candidates = images.iter().enumerate().filter(|(_, i)| is_candidate(i));
+    candidates
+        .clone()
+        .filter(|(_, i)| f32::from(i.height) >= height)
+        .min_by_key(|(_, i)| i.height)
+        .or_else(|| candidates.max_by_key(|(_, i)| i.height))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(height: u16, is_loaded: bool) -> Image {
+        Image {
+            url: String::new(),
+            name: height.to_string(),
+            mime: "image/png".to_string(),
+            height,
+            width: height,
+            bytes: is_loaded.then(Vec::new),
+        }
+    }
+
+    #[test]
+    fn test_best_for_height() {
+        let images = vec![image(32, true), image(64, true), image(128, false)];
+
+        let best = |height| Image::best_for_height(&images, height).map(|i| i.height);
+        assert_eq!(best(20.0), Some(32));
+        assert_eq!(best(48.0), Some(64));
+        assert_eq!(best(100.0), Some(64));
+        assert_eq!(Image::best_for_height(&[], 32.0), None);
+    }
+
+    #[test]
+    fn test_best_for_height_mut() {
+        let mut images = vec![image(32, false), image(64, false), image(128, false)];
+
+        assert_eq!(Image::native_height(&images), Some(32));
+        let mut best = |height| Image::best_for_height_mut(&mut images, height).map(|i| i.height);
+        assert_eq!(best(48.0), Some(64));
+        assert_eq!(best(200.0), Some(128));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::dgg::models::flair::FlairKind;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -10,3 +11,54 @@ pub struct User {
     #[serde(with = "crate::common::serde::datetime::ymd_hms_utc")]
     pub created_date: DateTime<Utc>,
 }
+
+impl User {
+    /// The highest subscription tier among the user's features, or 0 if they aren't subscribed.
+    pub fn sub_tier(&self) -> u8 {
+        self.features
+            .iter()
+            .filter_map(|feature| FlairKind::from(feature.as_str()).sub_tier())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Whether the user can mute and ban others.
+    pub fn is_moderator(&self) -> bool {
+        self.features.iter().any(|feature| {
+            matches!(
+                FlairKind::from(feature.as_str()),
+                FlairKind::Moderator | FlairKind::Admin
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(features: &[&str]) -> User {
+        User {
+            id: 1,
+            nick: "Bob".to_string(),
+            features: features.iter().map(|f| f.to_string()).collect(),
+            created_date: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn sub_tier_ignores_unknown_features() {
+        assert_eq!(
+            user(&["flair", "flairx", "flair999", "flair3"]).sub_tier(),
+            3
+        );
+        assert_eq!(user(&[]).sub_tier(), 0);
+    }
+
+    #[test]
+    fn moderators_have_the_moderator_or_admin_feature() {
+        assert!(user(&["flairx", "moderator"]).is_moderator());
+        assert!(user(&["admin"]).is_moderator());
+        assert!(!user(&["flair", "subscriber"]).is_moderator());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::dgg::export::TEXT_TIMESTAMP_FORMAT;
+use crate::dgg::models::event::{BaseEventData, ChatMessageData, Event, EventData};
+use crate::dgg::models::user::User;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use std::path::Path;
+
+/// The id given to the senders of logged messages, whose real ids aren't recorded.
+pub const LOGGED_USER_ID: u32 = 0;
+
+/// Parses a line of an OverRustle daily log, like `[2023-06-25 05:34:02 UTC] nick: message`,
+/// into a chat message.
+///
+/// The logs don't record the sender's features, so the sender has none, and is marked with
+/// [LOGGED_USER_ID] (see [is_from_log]).
+pub fn parse_line(line: &str) -> Option<Event> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (timestamp, rest) = line.strip_prefix('[')?.split_once("] ")?;
+    let (nick, text) = rest.split_once(": ")?;
+    if nick.is_empty() || nick.contains(char::is_whitespace) || text.trim().is_empty() {
+        return None;
+    }
+
+    let timestamp = NaiveDateTime::parse_from_str(timestamp, TEXT_TIMESTAMP_FORMAT).ok()?;
+    Some(chat_message(Utc.from_utc_datetime(&timestamp), nick, text))
+}
+
+/// Parses every line of a log, skipping the ones that aren't chat messages or have no text.
+pub fn parse_log(text: &str) -> Vec<Event> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+/// Whether the user is the sender of a message parsed from a log, rather than received live.
+pub fn is_from_log(user: &User) -> bool {
+    user.id == LOGGED_USER_ID
+}
+
+pub fn read_log(path: &Path) -> Result<Vec<Event>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(parse_log(&text))
+}
+
+fn chat_message(timestamp: DateTime<Utc>, nick: &str, text: &str) -> Event {
+    Event::ChatMessage(EventData {
+        data: ChatMessageData {
+            data: text.to_string(),
+        },
+        base: BaseEventData {
+            user: Some(User {
+                id: LOGGED_USER_ID,
+                nick: nick.to_string(),
+                features: Vec::new(),
+                created_date: timestamp,
+            }),
+            extra: None,
+            timestamp: Some(timestamp),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_messages_and_skips_other_lines() {
+        let log = "[2023-06-25 05:34:02 UTC] Destiny: hello: chat PepoTurkey\r\n\
+                   not a message\n\
+                   [2023-06-25 05:34:03 UTC] : empty nick\n\
+                   [2023-06-25 05:34:04 UTC] Bob: \n";
+
+        let events = parse_log(log);
+        assert_eq!(events.len(), 1);
+
+        let Event::ChatMessage(message) = &events[0] else {
+            panic!("Expected a chat message");
+        };
+        assert_eq!(message.base.nick(), Some("Destiny"));
+        assert!(is_from_log(message.base.user.as_ref().unwrap()));
+        assert_eq!(message.data.data, "hello: chat PepoTurkey");
+        assert_eq!(
+            message.base.timestamp,
+            Some(Utc.with_ymd_and_hms(2023, 6, 25, 5, 34, 2).unwrap())
+        );
+    }
+
+    #[test]
+    fn round_trips_through_the_serialized_form() {
+        let event = parse_line("[2023-06-25 05:34:02 UTC] Bob: hi").unwrap();
+        let raw = String::try_from(event.clone()).unwrap();
+        assert!(raw.starts_with("MSG "));
+        let Event::ChatMessage(message) = Event::try_from(raw.as_str()).unwrap() else {
+            panic!("Expected a chat message");
+        };
+        assert_eq!(message.base.nick(), Some("Bob"));
+        assert_eq!(
+            message.base.timestamp,
+            Some(Utc.with_ymd_and_hms(2023, 6, 25, 5, 34, 2).unwrap())
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// The parts of a chat message that a [SearchQuery] can match on.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchInput<'a> {
+    pub nick: &'a str,
+    pub features: &'a [String],
+    pub text: &'a str,
+    pub has_link: bool,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// A search over chat messages, parsed from text like `from:destiny has:link after:12:00 hello`.
+///
+/// Supported terms are `from:<nick>`, `flair:<feature>`, `has:link`, `-has:link`,
+/// `after:<time>` and `before:<time>`, where times are `HH:MM` today or `YYYY-MM-DD[THH:MM]`
+/// in UTC. Every other word has to appear in the message text, ignoring case.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Lowercase words.
+    pub words: Vec<String>,
+    pub nick: Option<String>,
+    pub flair: Option<String>,
+    pub has_link: Option<bool>,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+}
+
+impl SearchQuery {
+    /// Parses a query, with times of day taken to be on the date of `now`. Terms with an
+    /// invalid time are searched for as words instead.
+    pub fn parse(text: &str, now: DateTime<Utc>) -> Self {
+        let mut query = Self::default();
+        for term in text.split_whitespace() {
+            let (key, value) = term.split_once(':').unwrap_or(("", term));
+            match key.to_ascii_lowercase().as_str() {
+                "from" if !value.is_empty() => query.nick = Some(value.to_string()),
+                "flair" if !value.is_empty() => query.flair = Some(value.to_string()),
+                "has" if value.eq_ignore_ascii_case("link") => query.has_link = Some(true),
+                "-has" if value.eq_ignore_ascii_case("link") => query.has_link = Some(false),
+                "after" | "before" => match parse_time(value, now) {
+                    Some(time) if key.eq_ignore_ascii_case("after") => query.after = Some(time),
+                    Some(time) => query.before = Some(time),
+                    None => query.words.push(term.to_lowercase()),
+                },
+                _ => query.words.push(term.to_lowercase()),
+            }
+        }
+        query
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether the message matches every term. An empty query matches nothing.
+    pub fn matches(&self, input: &SearchInput<'_>) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        if let Some(nick) = &self.nick {
+            if !nick.eq_ignore_ascii_case(input.nick) {
+                return false;
+            }
+        }
+
+        if let Some(flair) = &self.flair {
+            if !input.features.iter().any(|f| f.eq_ignore_ascii_case(flair)) {
+                return false;
+            }
+        }
+
+        if let Some(has_link) = self.has_link {
+            if has_link != input.has_link {
+                return false;
+            }
+        }
+
+        if self.after.is_some_and(|after| input.sent_at < after)
+            || self.before.is_some_and(|before| input.sent_at > before)
+        {
+            return false;
+        }
+
+        let text = input.text.to_lowercase();
+        self.words.iter().all(|word| text.contains(word.as_str()))
+    }
+}
+
+fn parse_time(text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let naive = if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M") {
+        now.date_naive().and_time(time)
+    } else if let Ok(date_time) = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M") {
+        date_time
+    } else {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()?
+            .and_time(NaiveTime::MIN)
+    };
+    Some(Utc.from_utc_datetime(&naive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input<'a>(nick: &'a str, text: &'a str, sent_at: DateTime<Utc>) -> SearchInput<'a> {
+        SearchInput {
+            nick,
+            features: &[],
+            text,
+            has_link: false,
+            sent_at,
+        }
+    }
+
+    #[test]
+    fn parses_terms_and_words() {
+        let now = Utc.with_ymd_and_hms(2023, 6, 22, 18, 0, 0).unwrap();
+        let query = SearchQuery::parse("From:Destiny has:link after:12:30 Hello after:noon", now);
+
+        assert_eq!(query.nick.as_deref(), Some("Destiny"));
+        assert_eq!(query.has_link, Some(true));
+        assert_eq!(
+            query.after,
+            Some(Utc.with_ymd_and_hms(2023, 6, 22, 12, 30, 0).unwrap())
+        );
+        assert_eq!(query.words, vec!["hello", "after:noon"]);
+    }
+
+    #[test]
+    fn matches_every_term() {
+        let now = Utc.with_ymd_and_hms(2023, 6, 22, 18, 0, 0).unwrap();
+        let query = SearchQuery::parse("from:destiny before:2023-06-22T17:00 HELLO", now);
+        let earlier = now - chrono::Duration::hours(2);
+
+        assert!(query.matches(&input("Destiny", "well hello there", earlier)));
+        assert!(!query.matches(&input("Destiny", "well hello there", now)));
+        assert!(!query.matches(&input("Bob", "well hello there", earlier)));
+        assert!(!query.matches(&input("Destiny", "goodbye", earlier)));
+        assert!(!SearchQuery::parse("", now).matches(&input("Destiny", "hello", earlier)));
+    }
+}
@@ -14,6 +14,9 @@ use url::Url;
 pub struct CdnClient {
     pub url: Url,
     cache: Option<JsonCache>,
+    /// How many pixels each pixel of an image's native size is drawn with, which decides
+    /// which of its resolutions is downloaded.
+    image_scale: f32,
 }
 
 impl CdnClient {
@@ -21,9 +24,15 @@ impl CdnClient {
         Self {
             url,
             cache: cache_path.map(JsonCache::new),
+            image_scale: 1.0,
         }
     }
 
+    pub fn with_image_scale(mut self, image_scale: f32) -> Self {
+        self.image_scale = image_scale;
+        self
+    }
+
     pub async fn get_emotes(&mut self) -> Result<HashMap<String, Emote>> {
         if let Some(cache) = self.cache.as_mut() {
             let emotes = cache.get("emotes".to_string())?;
@@ -45,7 +54,7 @@ impl CdnClient {
             .context("Failed to parse emotes")?;
 
         for emote in emotes.iter_mut() {
-            ensure_image_bytes_exist(&mut emote.image[0]).await?;
+            self.download_best_image(&mut emote.image).await?;
         }
 
         let emotes_map = emotes
@@ -81,7 +90,7 @@ impl CdnClient {
             .context("Failed to parse flairs")?;
 
         for flair in flairs.iter_mut() {
-            ensure_image_bytes_exist(&mut flair.image[0]).await?;
+            self.download_best_image(&mut flair.image).await?;
         }
 
         let flairs_map = flairs
@@ -97,6 +106,18 @@ impl CdnClient {
         }
     }
 
+    /// Downloads the one resolution of an image that suits the image scale best.
+    async fn download_best_image(&self, images: &mut [Image]) -> Result<()> {
+        let Some(native_height) = Image::native_height(images) else {
+            return Ok(());
+        };
+        let height = f32::from(native_height) * self.image_scale;
+        if let Some(image) = Image::best_for_height_mut(images, height) {
+            ensure_image_bytes_exist(image).await?;
+        }
+        Ok(())
+    }
+
     pub async fn get_image(&self, img: &Image) -> Result<Vec<u8>> {
         let url = img.url.parse()?;
         let bytes = get_image_bytes(url).await?;
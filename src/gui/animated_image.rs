@@ -0,0 +1,238 @@
+use anyhow::{anyhow, Result};
+use eframe::egui::{ColorImage, Context, Id, TextStyle, TextureId, Ui, Vec2};
+use egui_extras::image::load_image_bytes;
+use egui_extras::RetainedImage;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Frame, ImageFormat};
+use std::io::Cursor;
+use std::time::Duration;
+
+/// Browsers play GIF frames with a shorter delay than this at the default speed instead.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+/// The line height that the image sizes given by dgg's CDN are meant for.
+const NATIVE_LINE_HEIGHT: f32 = 16.0;
+
+/// An image with one or more frames, played in a loop using the shared animation clock (see
+/// [advance_animations]).
+pub struct AnimatedImage {
+    frames: Vec<(RetainedImage, Duration)>,
+    duration: Duration,
+    /// The size the image is meant to be shown at, which may be smaller than its resolution.
+    size: Vec2,
+}
+
+impl AnimatedImage {
+    /// Decodes every frame of a GIF, APNG or animated WebP, or the only frame of any other image.
+    pub fn from_bytes(debug_name: &str, bytes: &[u8]) -> Result<Self> {
+        let frames = match image::guess_format(bytes) {
+            Ok(ImageFormat::Gif) => Some(GifDecoder::new(Cursor::new(bytes))?.into_frames()),
+            Ok(ImageFormat::Png) => {
+                let decoder = PngDecoder::new(Cursor::new(bytes))?;
+                decoder.is_apng().then(|| decoder.apng().into_frames())
+            }
+            Ok(ImageFormat::WebP) => {
+                let decoder = WebPDecoder::new(Cursor::new(bytes))?;
+                decoder.has_animation().then(|| decoder.into_frames())
+            }
+            _ => None,
+        };
+
+        let frames = match frames {
+            Some(frames) => frames
+                .collect_frames()?
+                .into_iter()
+                .enumerate()
+                .map(|(i, frame)| {
+                    let delay = frame_delay(&frame);
+                    let name = format!("{}#{}", debug_name, i);
+                    (
+                        RetainedImage::from_color_image(name, color_image(frame)),
+                        delay,
+                    )
+                })
+                .collect(),
+            None => {
+                let image = load_image_bytes(bytes).map_err(|e| anyhow!(e))?;
+                vec![(
+                    RetainedImage::from_color_image(debug_name, image),
+                    Duration::ZERO,
+                )]
+            }
+        };
+
+        Self::from_frames(frames)
+    }
+
+    fn from_frames(frames: Vec<(RetainedImage, Duration)>) -> Result<Self> {
+        if frames.is_empty() {
+            return Err(anyhow!("Image has no frames"));
+        }
+
+        let duration = frames.iter().map(|(_, delay)| *delay).sum();
+        let size = frames[0].0.size_vec2();
+        Ok(Self {
+            frames,
+            duration,
+            size,
+        })
+    }
+
+    pub fn with_size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    pub fn size_vec2(&self) -> Vec2 {
+        self.size
+    }
+
+    /// The size to draw the image at next to body text, multiplied by `scale`.
+    pub fn display_size(&self, ui: &Ui, scale: f32) -> Vec2 {
+        self.size * (ui.text_style_height(&TextStyle::Body) / NATIVE_LINE_HEIGHT) * scale
+    }
+
+    /// The texture of the frame to show now, requesting a repaint for when the next one is due.
+    pub fn texture_id(&self, ctx: &Context) -> TextureId {
+        if !self.is_animated() {
+            return self.frames[0].0.texture_id(ctx);
+        }
+
+        let clock = animation_clock(ctx);
+        let (index, remaining) = self.frame_at(Duration::from_secs_f64(clock.elapsed));
+        if !clock.is_paused {
+            ctx.request_repaint_after(remaining);
+        }
+        self.frames[index].0.texture_id(ctx)
+    }
+
+    /// The index of the frame shown after `elapsed`, and how long it is still shown for.
+    fn frame_at(&self, elapsed: Duration) -> (usize, Duration) {
+        if self.duration.is_zero() {
+            return (0, Duration::MAX);
+        }
+
+        let mut time = Duration::from_nanos((elapsed.as_nanos() % self.duration.as_nanos()) as u64);
+        for (i, (_, delay)) in self.frames.iter().enumerate() {
+            if time < *delay {
+                return (i, *delay - time);
+            }
+            time -= *delay;
+        }
+        (0, self.frames[0].1)
+    }
+}
+
+fn frame_delay(frame: &Frame) -> Duration {
+    let (numerator, denominator) = frame.delay().numer_denom_ms();
+    let delay = Duration::from_micros(1000 * numerator as u64 / denominator.max(1) as u64);
+    if delay < MIN_FRAME_DELAY {
+        DEFAULT_FRAME_DELAY
+    } else {
+        delay
+    }
+}
+
+fn color_image(frame: Frame) -> ColorImage {
+    let buffer = frame.into_buffer();
+    let size = [buffer.width() as usize, buffer.height() as usize];
+    ColorImage::from_rgba_unmultiplied(size, buffer.as_flat_samples().as_slice())
+}
+
+/// How long animations have been playing, which doesn't advance while they are paused.
+#[derive(Debug, Clone, Copy, Default)]
+struct AnimationClock {
+    elapsed: f64,
+    last_time: Option<f64>,
+    is_paused: bool,
+}
+
+fn emote_scale_id() -> Id {
+    Id::new("dgg_emote_scale")
+}
+
+/// Sets the scale that emotes are drawn at, for all views.
+pub fn set_emote_scale(ctx: &Context, scale: f32) {
+    ctx.data_mut(|d| d.insert_temp(emote_scale_id(), scale));
+}
+
+pub fn emote_scale(ctx: &Context) -> f32 {
+    ctx.data(|d| d.get_temp(emote_scale_id())).unwrap_or(1.0)
+}
+
+fn animation_clock_id() -> Id {
+    Id::new("dgg_animation_clock")
+}
+
+fn animation_clock(ctx: &Context) -> AnimationClock {
+    ctx.data(|d| d.get_temp(animation_clock_id()))
+        .unwrap_or_default()
+}
+
+/// The time animations have been playing in seconds, for animations that are drawn by hand.
+/// Requests a repaint unless they are paused.
+pub fn animate(ctx: &Context) -> f64 {
+    let clock = animation_clock(ctx);
+    if !clock.is_paused {
+        ctx.request_repaint();
+    }
+    clock.elapsed
+}
+
+/// Advances the clock that all [AnimatedImage]s are played by, unless `is_paused`. Has to be
+/// called once per frame.
+pub fn advance_animations(ctx: &Context, is_paused: bool) {
+    let time = ctx.input(|i| i.time);
+    let mut clock = animation_clock(ctx);
+    if !is_paused {
+        clock.elapsed += time - clock.last_time.unwrap_or(time);
+    }
+    clock.last_time = Some(time);
+    clock.is_paused = is_paused;
+    ctx.data_mut(|d| d.insert_temp(animation_clock_id(), clock));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(delays: &[u64]) -> AnimatedImage {
+        let frames = delays
+            .iter()
+            .map(|delay| {
+                (
+                    RetainedImage::from_color_image(
+                        "frame",
+                        ColorImage::new([1, 1], Default::default()),
+                    ),
+                    Duration::from_millis(*delay),
+                )
+            })
+            .collect();
+        AnimatedImage::from_frames(frames).unwrap()
+    }
+
+    #[test]
+    fn frames_loop_by_their_delays() {
+        let image = frames(&[100, 50, 200]);
+
+        assert_eq!(
+            image.frame_at(Duration::ZERO),
+            (0, Duration::from_millis(100))
+        );
+        assert_eq!(
+            image.frame_at(Duration::from_millis(120)),
+            (1, Duration::from_millis(30))
+        );
+        assert_eq!(
+            image.frame_at(Duration::from_millis(350 + 160)),
+            (2, Duration::from_millis(190))
+        );
+    }
+}
@@ -8,12 +8,12 @@ use crate::gui::{View, ViewMut};
 use anyhow::{bail, Result};
 use dgg::dgg::models::emote::Emote;
 use dgg::dgg::models::event;
-use dgg::dgg::models::event::Event;
+use dgg::dgg::models::event::{BaseEventData, Event};
 use dgg::dgg::models::flair::Flair;
+use dgg::settings::SystemMessageKind;
 use eframe::egui;
 use eframe::egui::{ScrollArea, Widget};
 use futures_util::TryFutureExt;
-use palette::white_point::E;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::sync::mpsc::error::TryRecvError;
@@ -32,17 +32,18 @@ pub struct ChatApp {
 impl ChatApp {
     pub fn new(
         cc: &eframe::CreationContext<'_>,
+        config: ChatAppConfig,
         event_rx: mpsc::Receiver<Event>,
         command_tx: mpsc::Sender<Command>,
         flairs_rx: oneshot::Receiver<HashMap<String, Flair>>,
         emotes_rx: oneshot::Receiver<HashMap<String, Emote>>,
     ) -> Self {
         ChatApp {
-            chat_view: ChatView::new(command_tx),
+            chat_view: ChatView::new(command_tx, &config),
+            config,
             event_rx: Some(event_rx),
             flairs_rx: Some(flairs_rx),
             emotes_rx: Some(emotes_rx),
-            ..Default::default()
         }
     }
 }
@@ -55,7 +56,7 @@ impl eframe::App for ChatApp {
         if let Some(flairs_rx) = &mut self.flairs_rx {
             if let Ok(flairs) = flairs_rx.try_recv() {
                 self.chat_view
-                    .set_flairs(flairs)
+                    .set_flairs(flairs, ctx.pixels_per_point())
                     .expect("Failed to set flairs");
                 self.flairs_rx = None;
             }
@@ -64,26 +65,45 @@ impl eframe::App for ChatApp {
         if let Some(emotes_rx) = &mut self.emotes_rx {
             if let Ok(emotes) = emotes_rx.try_recv() {
                 self.chat_view
-                    .set_emotes(emotes)
+                    .set_emotes(emotes, ctx.pixels_per_point())
                     .expect("Failed to set emotes");
                 self.emotes_rx = None;
             }
         }
 
         if let Some(event_rx) = self.event_rx.as_mut() {
-            handle_event(event_rx, &mut self.chat_view).unwrap_or_else(|e| {
-                panic!("Error handling event: {:?}", e);
-            });
+            handle_events(event_rx, &mut self.chat_view);
         }
 
         #[cfg(not(target_arch = "wasm32"))] // no File->Quit on web pages!
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    if ui.button("Export…").clicked() {
+                        self.chat_view.open_export();
+                        ui.close_menu();
+                    }
+                    if ui.button("Import OverRustle log…").clicked() {
+                        self.chat_view.open_import();
+                        ui.close_menu();
+                    }
                     if ui.button("Quit").clicked() {
                         _frame.close();
                     }
                 });
+                ui.menu_button("Ignore", |ui| {
+                    self.chat_view.show_ignore_menu(ui);
+                });
+                let whispers = match self.chat_view.unread_whisper_count() {
+                    0 => "Whispers".to_string(),
+                    count => format!("Whispers ({})", count),
+                };
+                if ui.button(whispers).clicked() {
+                    self.chat_view.open_whispers();
+                }
+                if ui.button("Settings").clicked() {
+                    self.chat_view.open_settings();
+                }
                 ui.heading("Destiny.gg Chat");
                 egui::warn_if_debug_build(ui);
             });
@@ -95,35 +115,56 @@ impl eframe::App for ChatApp {
     }
 }
 
-fn handle_event(event_rx: &mut mpsc::Receiver<Event>, chat_view: &mut ChatView) -> Result<()> {
-    match event_rx.try_recv() {
-        Ok(Event::ChatMessage(msg)) => chat_view.add_message(msg)?,
-        Ok(Event::Connected(data)) => {
+/// Handles the events received since the last frame. An event that can't be shown, such as a
+/// replayed or imported message without a sender, is skipped.
+fn handle_events(event_rx: &mut mpsc::Receiver<Event>, chat_view: &mut ChatView) {
+    loop {
+        match event_rx.try_recv() {
+            Ok(event) => handle_event(event, chat_view).unwrap_or_else(|e| {
+                warn!("Skipping event: {:?}", e);
+            }),
+            Err(TryRecvError::Empty) => return,
+            Err(e) => {
+                panic!("Error receiving event: {:?}", e);
+            }
+        }
+    }
+}
+
+fn handle_event(event: Event, chat_view: &mut ChatView) -> Result<()> {
+    match event {
+        Event::ChatMessage(msg) => chat_view.add_message(msg)?,
+        Event::Whisper(whisper) => chat_view.add_whisper(whisper)?,
+        Event::ServedConnections(names) => chat_view.set_connected_users(names.data.users),
+        Event::UserJoined(BaseEventData {
+            user: Some(user), ..
+        }) => chat_view.add_connected_user(user),
+        Event::UserQuit(BaseEventData {
+            user: Some(user), ..
+        }) => chat_view.remove_connected_user(user),
+        Event::Connected(data) => {
             info!(
                 "Connected as {}",
-                data.user.map(|u| u.nick).unwrap_or("Anonymous".to_string())
+                data.user.as_ref().map_or("Anonymous", |u| u.nick.as_str())
             );
+            chat_view.set_current_user(data.user);
         }
-        Ok(Event::Pin(pin)) => {
-            info!("Pin: {:?}", pin)
-        }
-        Ok(Event::Broadcast(broadcast)) => {
-            info!("Broadcast: {:?}", broadcast)
-        }
-        Ok(Event::ErrorMessage(err)) => {
+        Event::Pin(pin) => chat_view.set_pin(pin)?,
+        Event::Broadcast(broadcast) => chat_view.add_broadcast(broadcast),
+        Event::Mute(mute) => chat_view.add_moderation(SystemMessageKind::Mute, mute),
+        Event::Unmute(unmute) => chat_view.add_moderation(SystemMessageKind::Unmute, unmute),
+        Event::Ban(ban) => chat_view.add_moderation(SystemMessageKind::Ban, ban),
+        Event::Unban(unban) => chat_view.add_moderation(SystemMessageKind::Unban, unban),
+        Event::SubOnly(sub_only) => chat_view.add_moderation(SystemMessageKind::SubOnly, sub_only),
+        Event::ErrorMessage(err) => {
             error!("Error: {:?}", err)
         }
-        Ok(Event::Unknown(msg)) => {
+        Event::Unknown(msg) => {
             warn!("Unknown: {:?}", msg)
         }
-        Err(TryRecvError::Empty) => {}
-        Err(e) => {
-            panic!("Error receiving event: {:?}", e);
-        }
-        Ok(e) => {
+        e => {
             debug!(" {:?}", e);
         }
     };
-
     Ok(())
 }
@@ -1,27 +1,129 @@
+use crate::gui::commands::ACTION_PREFIX;
 use anyhow::{bail, Context, Error, Result};
 use dgg::config::ChatAppConfig;
 use dgg::dgg::chat::chat_client::{ChatClient, WebSocketMessage};
-use dgg::dgg::models::event::Event;
+use dgg::dgg::export::{export, ExportRequest};
+use dgg::dgg::history::{self, HistoryQuery, HistoryStore};
+use dgg::dgg::models::event::{BanRequestData, Event};
+use dgg::dgg::overrustle;
 use dgg::dgg::utilities::cdn::CdnClient;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::pin::pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use dgg::dgg::chat::chat_client;
 use dgg::dgg::models::emote::Emote;
 use dgg::dgg::models::flair::Flair;
 use tokio::sync::mpsc::error::TryRecvError;
-use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::mpsc::{unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender};
 use tokio::sync::{oneshot, Mutex};
 use tokio::{join, select};
 
 /// A command sent to the ChatAppServices.
-#[derive(Debug)]
+///
+/// Commands that only affect the UI (see [Command::is_local]) are handled by the views instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Command {
     SendMessage(String),
+    SendAction(String),
+    Whisper {
+        nick: String,
+        message: String,
+    },
+    Mute {
+        nick: String,
+        duration: Option<Duration>,
+    },
+    Unmute(String),
+    Ban {
+        nick: String,
+        reason: String,
+        /// `None` for a permanent ban.
+        duration: Option<Duration>,
+        ban_ip: bool,
+    },
+    Unban(String),
+    SubOnly(bool),
+    /// Export a time range of the chat history to a file.
+    Export {
+        request: Box<ExportRequest>,
+        status: StatusSender,
+    },
+    /// Load an OverRustle log and send back its messages, optionally keeping them in the
+    /// history.
+    ImportLog {
+        path: PathBuf,
+        save_to_history: bool,
+        log: Reply<Vec<Event>>,
+        status: StatusSender,
+    },
+    Ignore(String),
+    Unignore(String),
+    Highlight(String),
+    Unhighlight(String),
+    Clear,
+    Help,
+}
+
+impl Command {
+    /// Whether the command only changes the UI, such as the ignore list or the shown messages.
+    /// These are run by [crate::gui::views::chat_view::ChatView::run_command], since the state
+    /// they change lives there, and the services reject them.
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self,
+            Command::Ignore(_)
+                | Command::Unignore(_)
+                | Command::Highlight(_)
+                | Command::Unhighlight(_)
+                | Command::Clear
+                | Command::Help
+        )
+    }
+}
+
+/// Sends the results of a long-running command, such as an import, back to the view that
+/// started it.
+#[derive(Debug, Clone)]
+pub struct Reply<T>(UnboundedSender<T>);
+
+/// Reports the outcome of a command, as a message or an error.
+pub type StatusSender = Reply<Result<String, String>>;
+
+impl<T> Reply<T> {
+    /// A sender, and the receiver that the view checks for the results.
+    pub fn channel() -> (Self, UnboundedReceiver<T>) {
+        let (tx, rx) = unbounded_channel();
+        (Self(tx), rx)
+    }
+
+    pub fn send(&self, value: T) {
+        // The view may be gone, in which case there is no one left to tell.
+        let _ = self.0.send(value);
+    }
+}
+
+impl StatusSender {
+    pub fn report(&self, result: Result<String>) {
+        match &result {
+            Ok(message) => info!("{}", message),
+            Err(e) => error!("{:?}", e),
+        }
+        self.send(result.map_err(|e| format!("{:#}", e)));
+    }
+}
+
+/// Commands are compared by content, so two replies are equal if they go to the same view.
+impl<T> PartialEq for Reply<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.same_channel(&other.0)
+    }
 }
 
+impl<T> Eq for Reply<T> {}
+
 #[derive(Debug)]
 /// Receives commands from the UI, and sends events and other data back.
 pub struct ChatAppServices {
@@ -30,6 +132,8 @@ pub struct ChatAppServices {
     command_rx: Receiver<Command>,
     flairs_tx: oneshot::Sender<HashMap<String, Flair>>,
     emotes_tx: oneshot::Sender<HashMap<String, Emote>>,
+    /// The scale factor of the display, used to download images at a fitting resolution.
+    pixels_per_point: f32,
 }
 
 impl ChatAppServices {
@@ -46,15 +150,23 @@ impl ChatAppServices {
             command_rx,
             flairs_tx,
             emotes_tx,
+            pixels_per_point: 1.0,
         }
     }
 
+    pub fn with_pixels_per_point(mut self, pixels_per_point: f32) -> Self {
+        self.pixels_per_point = pixels_per_point;
+        self
+    }
+
     pub async fn start(self) {
         info!("Starting app services...");
+        let image_scale = self.pixels_per_point * self.config.settings.display.emote_scale;
         let mut cdn_client =
-            CdnClient::new(self.config.get_cdn_url(), self.config.cache_path.clone());
+            CdnClient::new(self.config.get_cdn_url(), self.config.cache_path.clone())
+                .with_image_scale(image_scale);
+        let history = open_history(&self.config);
         let mut chat_client = ChatClient::new(self.config);
-        chat_client.connect().await.unwrap();
 
         let Self {
             mut event_tx,
@@ -64,17 +176,76 @@ impl ChatAppServices {
             ..
         } = self;
 
+        if let Some(history) = history.as_ref() {
+            send_scrollback(history, &event_tx).await;
+        }
+        let history_tx = history.clone().map(|history| {
+            let (history_tx, history_rx) = unbounded_channel();
+            tokio::spawn(store_events(history, history_rx));
+            history_tx
+        });
+        chat_client.connect().await.unwrap();
+
         join! {
             send_cdn_data( flairs_tx, emotes_tx, cdn_client),
             async move {
                 loop {
-                    handle_next_command_or_event(&mut command_rx, &mut event_tx, &mut chat_client).await;
+                    handle_next_command_or_event(&mut command_rx, &mut event_tx, &mut chat_client, history.as_ref(), history_tx.as_ref()).await;
                 }
             }
         };
     }
 }
 
+/// The number of events replayed from the history on startup.
+const SCROLLBACK_LENGTH: usize = 200;
+
+fn open_history(config: &ChatAppConfig) -> Option<HistoryStore> {
+    let path = config.get_cache_dir()?.join("history.sqlite");
+    HistoryStore::open(&path)
+        .map_err(|e| error!("Failed to open chat history: {:?}", e))
+        .ok()
+}
+
+/// Sends the latest messages from the history, so that the chat isn't empty after a restart.
+async fn send_scrollback(history: &HistoryStore, event_tx: &Sender<Event>) {
+    let events = match history
+        .query(HistoryQuery::scrollback(SCROLLBACK_LENGTH))
+        .await
+    {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Failed to load scrollback: {:?}", e);
+            return;
+        }
+    };
+
+    debug!("Replaying {} events from history", events.len());
+    for (_, event) in events {
+        event_tx.send(event).await.unwrap();
+    }
+}
+
+/// How long to collect received events before storing them together.
+const HISTORY_BATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stores the events sent on `rx` in the history, in one transaction per batch, so that writing
+/// doesn't hold up the chat.
+async fn store_events(history: HistoryStore, mut rx: UnboundedReceiver<Event>) {
+    while let Some(event) = rx.recv().await {
+        tokio::time::sleep(HISTORY_BATCH_INTERVAL).await;
+        let mut events = vec![event];
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+
+        trace!("Storing {} events", events.len());
+        history.insert_all(events).await.unwrap_or_else(|e| {
+            error!("Failed to store events: {:?}", e);
+        });
+    }
+}
+
 async fn emit_next_event(tx: &mut Sender<Event>, chat_client: &mut ChatClient) {
     if let Some(WebSocketMessage::Event(event)) = chat_client.get_next_message().await.unwrap() {
         trace!("Sending event: {:?}", event);
@@ -101,21 +272,115 @@ async fn send_cdn_data(
     send_flairs(flairs_tx, &mut cdn_client).await;
 }
 
+async fn handle_command(
+    command: Command,
+    chat_client: &mut ChatClient,
+    history: Option<&HistoryStore>,
+) -> Result<()> {
+    trace!("Handling command: {:?}", command);
+    match command {
+        Command::SendMessage(message) => chat_client.send_message(message).await,
+        Command::SendAction(message) => {
+            chat_client
+                .send_message(format!("{}{}", ACTION_PREFIX, message))
+                .await
+        }
+        Command::Whisper { nick, message } => chat_client.send_whisper(nick, message).await,
+        Command::Mute { nick, duration } => chat_client.mute(nick, duration).await,
+        Command::Unmute(nick) => chat_client.unmute(nick).await,
+        Command::Ban {
+            nick,
+            reason,
+            duration,
+            ban_ip,
+        } => {
+            chat_client
+                .ban(BanRequestData {
+                    nick,
+                    reason,
+                    is_permanent: duration.is_none(),
+                    duration: duration.map(chat_client::duration_nanos).transpose()?,
+                    ban_ip,
+                })
+                .await
+        }
+        Command::Unban(nick) => chat_client.unban(nick).await,
+        Command::SubOnly(enabled) => chat_client.set_sub_only(enabled).await,
+        Command::Export { request, status } => {
+            let history = history.cloned();
+            tokio::spawn(async move { status.report(run_export(history, *request).await) });
+            Ok(())
+        }
+        Command::ImportLog {
+            path,
+            save_to_history,
+            log,
+            status,
+        } => {
+            let history = history.cloned();
+            let result = run_import(path, save_to_history, history, log);
+            tokio::spawn(async move { status.report(result.await) });
+            Ok(())
+        }
+        command => bail!("{:?} should be handled by the UI", command),
+    }
+}
+
+/// Exports the history in the background, so that the chat keeps updating meanwhile.
+async fn run_export(history: Option<HistoryStore>, request: ExportRequest) -> Result<String> {
+    let history = history.context("Chat history is not available")?;
+    let path = request.path.clone();
+    let count = export(&history, request).await?;
+    Ok(format!("Exported {} events to {}", count, path.display()))
+}
+
+/// Loads a log in the background, so that live events keep arriving meanwhile, and sends its
+/// messages back to be shown apart from the live chat.
+async fn run_import(
+    path: PathBuf,
+    save_to_history: bool,
+    history: Option<HistoryStore>,
+    log: Reply<Vec<Event>>,
+) -> Result<String> {
+    let events = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || overrustle::read_log(&path)
+    })
+    .await??;
+    if save_to_history {
+        let history = history.context("Chat history is not available")?;
+        history.insert_all(events.clone()).await?;
+    }
+
+    let count = events.len();
+    log.send(events);
+    Ok(format!("Loaded {} messages from {}", count, path.display()))
+}
+
 async fn handle_next_command_or_event(
     command_rx: &mut Receiver<Command>,
     event_tx: &mut Sender<Event>,
     chat_client: &mut ChatClient,
+    history: Option<&HistoryStore>,
+    history_tx: Option<&UnboundedSender<Event>>,
 ) {
     select!(
         command = command_rx.recv() => {
-            if let Some(Command::SendMessage(message)) = command {
-                trace!("Sending message: {:?}", message);
-                chat_client.send_message(message).await.unwrap();
+            if let Some(command) = command {
+                handle_command(command, chat_client, history).await.unwrap_or_else(|e| {
+                    error!("Failed to handle command: {:?}", e);
+                });
             }
         }
         event = chat_client.get_next_message() =>
         {
             if let Ok(Some(WebSocketMessage::Event(event))) = event {
+                if let Some(history_tx) = history_tx.filter(|_| history::is_stored(&event)) {
+                    history_tx.send(event.clone()).unwrap_or_else(|e| {
+                        error!("Failed to store event: {:?}", e);
+                    });
+                }
+
                 trace!("Sending event: {:?}", event);
                 event_tx.send(event).await.unwrap();
             }
@@ -0,0 +1,327 @@
+use crate::gui::app_services::Command;
+use anyhow::{anyhow, bail, Result};
+use dgg::dgg::chat::chat_client::MAX_MESSAGE_LENGTH;
+use std::time::Duration;
+
+/// Prepended to the text of [Command::SendAction] when it is sent.
+pub const ACTION_PREFIX: &str = "/me ";
+
+/// Usage and description of every slash command, as shown by `/help`.
+pub const COMMAND_HELP: &[(&str, &str)] = &[
+    ("/w, /msg <nick> <message>", "Whisper to a user"),
+    ("/me <message>", "Send an action"),
+    ("/ignore <nick>", "Hide messages from a user"),
+    ("/unignore <nick>", "Stop hiding messages from a user"),
+    ("/highlight <nick>", "Highlight messages from a user"),
+    (
+        "/unhighlight <nick>",
+        "Stop highlighting messages from a user",
+    ),
+    ("/mute <nick> [duration]", "Mute a user, e.g. for 10m"),
+    ("/unmute <nick>", "Unmute a user"),
+    ("/ban <nick> <duration|perm> <reason>", "Ban a user"),
+    (
+        "/ipban <nick> <duration|perm> <reason>",
+        "Ban a user and their IP",
+    ),
+    ("/unban <nick>", "Unban a user"),
+    ("/subonly <on|off>", "Toggle subscriber-only mode"),
+    ("/clear", "Clear the chat"),
+    ("/help", "Show this help"),
+];
+
+/// Parses the text of the chat input into a [Command]. Text not starting with `/` is a plain
+/// message, which keeps its leading whitespace, and errors contain usage information for
+/// malformed commands.
+pub fn parse_input(text: &str) -> Result<Command> {
+    let text = text.trim_end();
+    let Some(command_line) = text.strip_prefix('/') else {
+        return Ok(Command::SendMessage(text.to_string()));
+    };
+    let command_line = command_line.trim();
+
+    let (name, args) = command_line
+        .split_once(char::is_whitespace)
+        .map(|(name, args)| (name, args.trim()))
+        .unwrap_or((command_line, ""));
+    let name = name.to_ascii_lowercase();
+
+    let command = match name.as_str() {
+        "w" | "whisper" | "msg" | "message" | "tell" | "t" | "notify" => {
+            let (nick, message) = nick_and_rest(args)
+                .filter(|(_, message)| !message.is_empty())
+                .ok_or_else(|| usage(&name, "<nick> <message>"))?;
+            Command::Whisper {
+                nick,
+                message: message.to_string(),
+            }
+        }
+        "me" => {
+            if args.is_empty() {
+                return Err(usage(&name, "<message>"));
+            }
+            Command::SendAction(args.to_string())
+        }
+        "ignore" => Command::Ignore(single_nick(&name, args)?),
+        "unignore" => Command::Unignore(single_nick(&name, args)?),
+        "highlight" => Command::Highlight(single_nick(&name, args)?),
+        "unhighlight" => Command::Unhighlight(single_nick(&name, args)?),
+        "mute" => {
+            let (nick, duration) =
+                nick_and_rest(args).ok_or_else(|| usage(&name, "<nick> [duration]"))?;
+            let duration = match duration {
+                "" => None,
+                duration => Some(
+                    parse_duration(duration)
+                        .ok_or_else(|| anyhow!("Invalid duration: {}", duration))?,
+                ),
+            };
+            Command::Mute { nick, duration }
+        }
+        "unmute" => Command::Unmute(single_nick(&name, args)?),
+        "ban" | "ipban" => {
+            let usage = || usage(&name, "<nick> <duration|perm> <reason>");
+            let (nick, rest) = nick_and_rest(args).ok_or_else(usage)?;
+            let (duration, reason) = rest
+                .split_once(char::is_whitespace)
+                .map(|(duration, reason)| (duration, reason.trim()))
+                .filter(|(_, reason)| !reason.is_empty())
+                .ok_or_else(usage)?;
+            let duration = match duration.to_ascii_lowercase().as_str() {
+                "perm" | "permanent" => None,
+                _ => Some(
+                    parse_duration(duration)
+                        .ok_or_else(|| anyhow!("Invalid duration: {}", duration))?,
+                ),
+            };
+            Command::Ban {
+                nick,
+                reason: reason.to_string(),
+                duration,
+                ban_ip: name == "ipban",
+            }
+        }
+        "unban" => Command::Unban(single_nick(&name, args)?),
+        "subonly" => match args.to_ascii_lowercase().as_str() {
+            "on" => Command::SubOnly(true),
+            "off" => Command::SubOnly(false),
+            _ => return Err(usage(&name, "<on|off>")),
+        },
+        "clear" => Command::Clear,
+        "help" => Command::Help,
+        _ => bail!(
+            "Unknown command: /{}. Type /help for a list of commands.",
+            name
+        ),
+    };
+
+    Ok(command)
+}
+
+/// The length of the message sent by `command`, if it sends one.
+pub fn message_length(command: &Command) -> Option<usize> {
+    match command {
+        Command::SendMessage(message) | Command::Whisper { message, .. } => {
+            Some(message.chars().count())
+        }
+        Command::SendAction(message) => Some(ACTION_PREFIX.len() + message.chars().count()),
+        _ => None,
+    }
+}
+
+/// Splits a command sending a message longer than [MAX_MESSAGE_LENGTH] into commands sending
+/// consecutive parts of it.
+pub fn split_command(command: &Command) -> Vec<Command> {
+    match command {
+        Command::SendMessage(message) => split_message(message, MAX_MESSAGE_LENGTH)
+            .into_iter()
+            .map(Command::SendMessage)
+            .collect(),
+        Command::SendAction(message) => {
+            split_message(message, MAX_MESSAGE_LENGTH - ACTION_PREFIX.len())
+                .into_iter()
+                .map(Command::SendAction)
+                .collect()
+        }
+        Command::Whisper { nick, message } => split_message(message, MAX_MESSAGE_LENGTH)
+            .into_iter()
+            .map(|message| Command::Whisper {
+                nick: nick.clone(),
+                message,
+            })
+            .collect(),
+        command => vec![command.clone()],
+    }
+}
+
+/// Splits `text` into parts of at most `max_length` chars, breaking at whitespace where possible.
+fn split_message(text: &str, max_length: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        let split = match rest.char_indices().nth(max_length) {
+            None => rest.len(),
+            Some((limit, c)) => rest[..limit + c.len_utf8()]
+                .rfind(char::is_whitespace)
+                .filter(|&i| i > 0)
+                .unwrap_or(limit),
+        };
+        parts.push(rest[..split].trim_end().to_string());
+        rest = rest[split..].trim_start();
+    }
+
+    parts
+}
+
+fn usage(name: &str, args: &str) -> anyhow::Error {
+    anyhow!("Usage: /{} {}", name, args)
+}
+
+fn nick_and_rest(args: &str) -> Option<(String, &str)> {
+    if args.is_empty() {
+        return None;
+    }
+
+    let (nick, rest) = args
+        .split_once(char::is_whitespace)
+        .map(|(nick, rest)| (nick, rest.trim()))
+        .unwrap_or((args, ""));
+    Some((nick.trim_start_matches('@').to_string(), rest))
+}
+
+fn single_nick(name: &str, args: &str) -> Result<String> {
+    match nick_and_rest(args) {
+        Some((nick, "")) => Ok(nick),
+        _ => Err(usage(name, "<nick>")),
+    }
+}
+
+/// Parses durations like `30`, `30s`, `10m`, `1h` or `7d`, where seconds are the default unit.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.to_ascii_lowercase();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+
+    let seconds = match unit {
+        "" | "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hr" | "hrs" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        _ => return None,
+    };
+
+    let duration = Duration::from_secs(number.checked_mul(seconds)?);
+    // Durations are sent in nanoseconds, which have to fit in a u64.
+    u64::try_from(duration.as_nanos())
+        .is_ok()
+        .then_some(duration)
+}
+
+/// Formats a duration in its largest whole unit, like `parse_duration` reads it, e.g. `10m`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    [(24 * 60 * 60, "d"), (60 * 60, "h"), (60, "m")]
+        .into_iter()
+        .find(|(unit, _)| seconds >= *unit && seconds.is_multiple_of(*unit))
+        .map(|(unit, suffix)| format!("{}{}", seconds / unit, suffix))
+        .unwrap_or_else(|| format!("{}s", seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_is_a_message() {
+        assert_eq!(
+            parse_input("hello /w world ").unwrap(),
+            Command::SendMessage("hello /w world".to_string())
+        );
+        assert_eq!(
+            parse_input("  > indented").unwrap(),
+            Command::SendMessage("  > indented".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_whispers() {
+        let expected = Command::Whisper {
+            nick: "Destiny".to_string(),
+            message: "hi there".to_string(),
+        };
+
+        assert_eq!(parse_input("/w Destiny hi there").unwrap(), expected);
+        assert_eq!(parse_input("/MSG @Destiny   hi there").unwrap(), expected);
+        assert!(parse_input("/w Destiny").is_err());
+    }
+
+    #[test]
+    fn parses_moderation_commands() {
+        assert_eq!(
+            parse_input("/mute troll 10m").unwrap(),
+            Command::Mute {
+                nick: "troll".to_string(),
+                duration: Some(Duration::from_secs(600)),
+            }
+        );
+        assert_eq!(
+            parse_input("/ipban troll perm being a troll").unwrap(),
+            Command::Ban {
+                nick: "troll".to_string(),
+                reason: "being a troll".to_string(),
+                duration: None,
+                ban_ip: true,
+            }
+        );
+        assert_eq!(
+            parse_input("/subonly off").unwrap(),
+            Command::SubOnly(false)
+        );
+        assert!(parse_input("/ban troll 1d").is_err());
+        assert!(parse_input("/mute troll soon").is_err());
+    }
+
+    #[test]
+    fn splits_long_messages_at_whitespace() {
+        assert_eq!(
+            split_message("aaa bbb ccc", 7),
+            vec!["aaa bbb".to_string(), "ccc".to_string()]
+        );
+        assert_eq!(
+            split_message("aaaaaaaaaa b", 4),
+            vec!["aaaa", "aaaa", "aa b"]
+        );
+
+        let message = "word ".repeat(300);
+        let parts = split_command(&Command::SendAction(message.clone()));
+        assert_eq!(parts.len(), 3);
+        assert!(parts
+            .iter()
+            .all(|part| message_length(part).unwrap() <= MAX_MESSAGE_LENGTH));
+    }
+
+    #[test]
+    fn formats_durations_in_largest_unit() {
+        assert_eq!(format_duration(Duration::from_secs(600)), "10m");
+        assert_eq!(format_duration(Duration::from_secs(90)), "90s");
+        assert_eq!(format_duration(Duration::from_secs(2 * 24 * 60 * 60)), "2d");
+        assert_eq!(
+            parse_duration(&format_duration(Duration::from_secs(7200))),
+            Some(Duration::from_secs(7200))
+        );
+        assert_eq!(parse_duration("99999999999999999d"), None);
+        assert_eq!(parse_duration("999999999999s"), None);
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_commands() {
+        assert!(parse_input("/dance").is_err());
+        assert!(parse_input("/ignore").is_err());
+        assert!(parse_input("/ignore two nicks").is_err());
+        assert!(parse_input("/subonly maybe").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use eframe::egui::{Response, Ui};
 
+mod animated_image;
 pub mod app;
 pub mod app_services;
+pub mod commands;
+pub mod ui_action;
 pub mod views;
 
 /// Like `eframe::egui::Widget` but doesn't take ownership of self.
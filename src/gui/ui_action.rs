@@ -0,0 +1,38 @@
+use crate::gui::app_services::Command;
+use eframe::egui::{Context, Id};
+
+/// An action requested by a nested view (e.g. from a context menu) that has to be handled by a
+/// view further up, which owns the state it changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UiAction {
+    Command(Command),
+    OpenUserCard(String),
+    /// Insert a nick into the chat input.
+    Mention(String),
+    /// Open the whisper conversation with a nick.
+    StartWhisper(String),
+    /// A whisper was sent from the chat input, and belongs in its conversation.
+    WhisperSent {
+        nick: String,
+        message: String,
+    },
+}
+
+impl UiAction {
+    fn id() -> Id {
+        Id::new("dgg_ui_actions")
+    }
+
+    /// Queues the action, to be handled later in the frame.
+    pub fn push(self, ctx: &Context) {
+        ctx.data_mut(|d| {
+            d.get_temp_mut_or_default::<Vec<UiAction>>(Self::id())
+                .push(self)
+        });
+    }
+
+    /// Takes all actions queued so far.
+    pub fn take_all(ctx: &Context) -> Vec<UiAction> {
+        ctx.data_mut(|d| std::mem::take(d.get_temp_mut_or_default::<Vec<UiAction>>(Self::id())))
+    }
+}
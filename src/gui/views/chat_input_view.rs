@@ -1,51 +1,379 @@
 use crate::gui::app_services::Command;
+use crate::gui::commands::{message_length, parse_input, split_command, COMMAND_HELP};
+use crate::gui::ui_action::UiAction;
+use crate::gui::views::emote_picker::EmotePicker;
+use crate::gui::views::input_history::InputHistory;
+use crate::gui::views::tab_completion::TabCompleter;
 use crate::gui::{View, ViewMut};
 use anyhow::Context;
+use dgg::dgg::chat::chat_client::MAX_MESSAGE_LENGTH;
 use eframe::egui;
-use eframe::egui::{Response, Ui, Widget};
+use eframe::egui::text::{CCursor, CCursorRange};
+use eframe::egui::text_edit::TextEditState;
+use eframe::egui::{
+    Align, Align2, Color32, Id, Key, Layout, Modifiers, Pos2, Response, TextStyle, Ui, Vec2, Widget,
+};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use tokio::sync::mpsc::Sender;
 
 #[derive(Default)]
 pub struct ChatInputView {
     pub text: String,
+    pub tab_completer: TabCompleter,
+    pub emote_picker: EmotePicker,
+    history: InputHistory,
     command_tx: Option<Sender<Command>>,
+    notice: Option<InputNotice>,
+    pending_edit: Option<InputEdit>,
+}
+
+/// An edit requested from outside the input, applied when it is next shown.
+enum InputEdit {
+    Insert(String),
+    Replace(String),
+}
+
+/// Shown above the input until the text changes, e.g. usage errors for malformed commands.
+struct InputNotice {
+    text: String,
+    is_error: bool,
+    input_text: String,
+    /// The parts a message that is too long can be sent as instead.
+    split: Vec<Command>,
 }
 
 impl ChatInputView {
-    pub fn new(command_tx: Sender<Command>) -> Self {
+    /// Creates the input, keeping its history and emote usage in `cache_dir` if given.
+    pub fn new(command_tx: Sender<Command>, cache_dir: Option<&Path>) -> Self {
         Self {
             command_tx: Some(command_tx),
+            emote_picker: EmotePicker::new(cache_dir.map(|dir| dir.join("emote_usage.json"))),
+            history: InputHistory::new(cache_dir.map(|dir| dir.join("input_history.json"))),
             ..Default::default()
         }
     }
 }
 
+impl ChatInputView {
+    /// Parses the input and sends or dispatches the resulting command. Returns `false` if the
+    /// input was invalid and should be kept for editing.
+    fn submit(&mut self, ui: &Ui) -> bool {
+        self.history.push(self.text.trim_end().to_string());
+
+        let command = match parse_input(&self.text) {
+            Ok(Command::Help) => {
+                let help = COMMAND_HELP
+                    .iter()
+                    .map(|(usage, description)| format!("{}  {}", usage, description))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.set_notice(help, false);
+                return true;
+            }
+            Ok(command) => command,
+            Err(e) => {
+                self.set_notice(e.to_string(), true);
+                return false;
+            }
+        };
+
+        if message_length(&command).is_some() {
+            self.emote_picker.note_sent(&self.text);
+        }
+
+        if let Some(length) = message_length(&command).filter(|&l| l > MAX_MESSAGE_LENGTH) {
+            self.set_notice(
+                format!(
+                    "Message is too long ({}/{} characters).",
+                    length, MAX_MESSAGE_LENGTH
+                ),
+                true,
+            );
+            if let Some(notice) = self.notice.as_mut() {
+                notice.split = split_command(&command);
+            }
+            return false;
+        }
+
+        self.send(ui, command);
+        true
+    }
+
+    fn send(&self, ui: &Ui, command: Command) {
+        if command.is_local() {
+            UiAction::Command(command).push(ui.ctx());
+        } else if let Some(command_tx) = self.command_tx.as_ref() {
+            if let Command::Whisper { nick, message } = &command {
+                UiAction::WhisperSent {
+                    nick: nick.clone(),
+                    message: message.clone(),
+                }
+                .push(ui.ctx());
+            }
+            command_tx
+                .blocking_send(command)
+                .expect("Failed to send command");
+        }
+    }
+
+    fn set_notice(&mut self, text: String, is_error: bool) {
+        self.notice = Some(InputNotice {
+            text,
+            is_error,
+            input_text: self.text.clone(),
+            split: Vec::new(),
+        });
+    }
+
+    /// Shows the notice, returning `true` if the offer to split a long message was accepted.
+    fn show_notice(&mut self, ui: &Ui, bottom_left: Pos2) -> bool {
+        let Some(notice) = &self.notice else {
+            return false;
+        };
+
+        let mut is_dismissed = false;
+        let mut is_split = false;
+        egui::Area::new(ui.id().with("chat_input_notice"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .fixed_pos(bottom_left)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal_top(|ui| {
+                        if notice.is_error {
+                            ui.colored_label(Color32::RED, &notice.text);
+                        } else {
+                            ui.label(&notice.text);
+                        }
+                        is_dismissed = ui.small_button("✖").clicked();
+                    });
+                    if notice.split.len() > 1 {
+                        is_split = ui
+                            .button(format!("Send as {} messages", notice.split.len()))
+                            .clicked();
+                    }
+                });
+            });
+
+        if is_split {
+            let split = self.notice.take().map(|n| n.split).unwrap_or_default();
+            for command in split {
+                self.send(ui, command);
+            }
+        } else if is_dismissed {
+            self.notice = None;
+        }
+        is_split
+    }
+
+    /// Consumes Enter without modifiers if the input is focused, so that it sends the message
+    /// instead of inserting a newline. Shift+Enter still inserts one.
+    fn consume_enter(&self, ui: &Ui, id: Id) -> bool {
+        ui.memory(|m| m.has_focus(id))
+            && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Enter))
+    }
+
+    /// Shows how long the message is, next to the bottom right corner of the input.
+    fn show_length_counter(&self, ui: &Ui, response: &Response) {
+        let Some(length) = parse_input(&self.text)
+            .ok()
+            .as_ref()
+            .and_then(message_length)
+            .filter(|&l| l > 0)
+        else {
+            return;
+        };
+
+        let color = if length > MAX_MESSAGE_LENGTH {
+            Color32::RED
+        } else {
+            ui.visuals().weak_text_color()
+        };
+        ui.painter().text(
+            response.rect.right_bottom() - Vec2::new(6.0, 4.0),
+            Align2::RIGHT_BOTTOM,
+            format!("{}/{}", length, MAX_MESSAGE_LENGTH),
+            TextStyle::Small.resolve(ui.style()),
+            color,
+        );
+    }
+
+    /// Recalls the history with Up and Down while the cursor is on the first or last line.
+    /// Returns `true` if the text was replaced.
+    fn recall_history(&mut self, ui: &Ui, id: Id) -> bool {
+        if !ui.memory(|m| m.has_focus(id)) {
+            return false;
+        }
+
+        let cursor = TextEditState::load(ui.ctx(), id)
+            .and_then(|state| state.ccursor_range())
+            .map_or(self.text.chars().count(), |range| range.primary.index);
+        let is_on_first_line = !self.text.chars().take(cursor).any(|c| c == '\n');
+        let is_on_last_line = !self.text.chars().skip(cursor).any(|c| c == '\n');
+
+        let entry =
+            if is_on_first_line && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowUp)) {
+                self.history.previous(&self.text)
+            } else if is_on_last_line
+                && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowDown))
+            {
+                self.history.next()
+            } else {
+                None
+            };
+
+        match entry {
+            Some(entry) => {
+                self.text = entry.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Inserts `word` at the cursor the next time the input is shown.
+    pub fn insert_word(&mut self, word: String) {
+        self.pending_edit = Some(InputEdit::Insert(word));
+    }
+
+    /// Replaces the text the next time the input is shown.
+    pub fn replace_text(&mut self, text: String) {
+        self.pending_edit = Some(InputEdit::Replace(text));
+    }
+
+    fn apply_pending_edit(&mut self, ui: &Ui, id: Id) {
+        match self.pending_edit.take() {
+            Some(InputEdit::Insert(word)) => self.insert_at_cursor(ui, id, &word),
+            Some(InputEdit::Replace(text)) => {
+                self.text = text;
+                let end = CCursor::new(self.text.chars().count());
+                let mut state = TextEditState::load(ui.ctx(), id).unwrap_or_default();
+                state.set_ccursor_range(Some(CCursorRange::one(end)));
+                state.store(ui.ctx(), id);
+                ui.memory_mut(|m| m.request_focus(id));
+            }
+            None => {}
+        }
+    }
+
+    /// Inserts a word at the cursor, or at the end if the input was never focused, and focuses
+    /// the input again.
+    fn insert_at_cursor(&mut self, ui: &Ui, id: Id, word: &str) {
+        let mut state = TextEditState::load(ui.ctx(), id).unwrap_or_default();
+        let len = self.text.chars().count();
+        let cursor = state
+            .ccursor_range()
+            .map_or(len, |range| range.primary.index.min(len));
+
+        let byte_index = self
+            .text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(i, _)| i);
+        let needs_space = self.text[..byte_index]
+            .chars()
+            .last()
+            .is_some_and(|c| !c.is_whitespace());
+        let insertion = format!("{}{} ", if needs_space { " " } else { "" }, word);
+        self.text.insert_str(byte_index, &insertion);
+
+        let new_cursor = CCursor::new(cursor + insertion.chars().count());
+        state.set_ccursor_range(Some(CCursorRange::one(new_cursor)));
+        state.store(ui.ctx(), id);
+        ui.memory_mut(|m| m.request_focus(id));
+    }
+
+    fn text_edit_id(&self, ui: &Ui) -> Id {
+        ui.id().with("chat_input_text_edit")
+    }
+
+    /// Consumes Tab and Shift+Tab if the input is focused, so they can be used for completion
+    /// instead of moving focus or indenting. Returns `Some(true)` for Shift+Tab.
+    fn consume_tab(&self, ui: &Ui, id: Id) -> Option<bool> {
+        if !ui.memory(|m| m.has_focus(id)) {
+            return None;
+        }
+
+        ui.input_mut(|i| {
+            if i.consume_key(Modifiers::SHIFT, Key::Tab) {
+                Some(true)
+            } else if i.consume_key(Modifiers::NONE, Key::Tab) {
+                Some(false)
+            } else {
+                None
+            }
+        })
+    }
+}
+
 impl ViewMut for ChatInputView {
     fn show(&mut self, ui: &mut Ui) -> Response {
-        let response = ui.text_edit_multiline(&mut self.text);
-
-        let sent = ui.ctx().input(|s| {
-            s.events.iter().any(|e| {
-                matches!(
-                    e,
-                    egui::Event::Key {
-                        key: egui::Key::Enter,
-                        pressed: true,
-                        ..
-                    }
-                )
+        let id = self.text_edit_id(ui);
+        self.apply_pending_edit(ui, id);
+        let tab = self.consume_tab(ui, id);
+        let sent = self.consume_enter(ui, id) && !self.text.trim().is_empty();
+        let is_recalled = self.recall_history(ui, id);
+
+        let (picker_button, mut output) = ui
+            .with_layout(Layout::right_to_left(Align::Min), |ui| {
+                let picker_button = ui.button("☺").on_hover_text("Emotes");
+                let output = egui::TextEdit::multiline(&mut self.text)
+                    .id(id)
+                    .lock_focus(true)
+                    .desired_width(ui.available_width())
+                    .show(ui);
+                (picker_button, output)
             })
-        });
+            .inner;
+        let response = output.response;
+        let cursor = output.cursor_range.map(|c| c.primary.ccursor.index);
 
-        if sent {
-            if let Some(command_tx) = self.command_tx.as_ref() {
-                let text = self.text.trim_end().to_string();
-                command_tx
-                    .blocking_send(Command::SendMessage(text))
-                    .expect("Failed to send message");
+        if is_recalled {
+            let end = CCursor::new(self.text.chars().count());
+            output.state.set_ccursor_range(Some(CCursorRange::one(end)));
+            output.state.store(ui.ctx(), id);
+        } else if let (Some(backwards), Some(cursor)) = (tab, cursor) {
+            if let Some(new_cursor) = self
+                .tab_completer
+                .complete(&mut self.text, cursor, backwards)
+            {
+                output
+                    .state
+                    .set_ccursor_range(Some(CCursorRange::one(CCursor::new(new_cursor))));
+                output.state.store(ui.ctx(), id);
             }
+        } else {
+            self.tab_completer.update(&self.text, cursor);
+        }
+
+        self.tab_completer
+            .show_suggestions(ui, response.rect.left_top());
+
+        self.show_length_counter(ui, &response);
+
+        if picker_button.clicked() {
+            self.emote_picker.is_open = !self.emote_picker.is_open;
+        }
+        if let Some(prefix) = self.emote_picker.show(ui, picker_button.rect.right_top()) {
+            self.insert_at_cursor(ui, id, &prefix);
+        }
+
+        if sent && self.submit(ui) {
+            self.text.clear();
+            self.tab_completer.update(&self.text, None);
+        }
+
+        if self
+            .notice
+            .as_ref()
+            .is_some_and(|n| n.input_text != self.text && n.is_error)
+        {
+            self.notice = None;
+        }
+        if self.show_notice(ui, response.rect.left_top()) {
             self.text.clear();
+            self.tab_completer.update(&self.text, None);
         }
 
         response
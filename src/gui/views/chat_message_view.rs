@@ -1,24 +1,31 @@
+use crate::gui::animated_image::{animate, emote_scale, AnimatedImage};
+use crate::gui::app_services::Command;
+use crate::gui::ui_action::UiAction;
 use crate::gui::views::chat_view::ChatView;
 use crate::gui::View;
-use dgg::dgg::models::emote::Emote;
+use chrono::{DateTime, Utc};
+use dgg::dgg::filter::FilterAction;
+use dgg::dgg::models::emote::{split_emote_modifiers, Emote, EmoteModifier};
+use dgg::dgg::models::flair::Flair;
+use dgg::settings::{CensorStyle, SystemMessageKind};
 use eframe::egui;
 use eframe::egui::{Response, Rgba, Ui, Widget};
-use egui_extras::RetainedImage;
 use linkify::{Link, LinkFinder, LinkKind};
 use palette::{FromColor, Hsv, Srgb};
 use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
-use serde_json::to_string;
-use std::cell::LazyCell;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::process::id;
 use std::rc::Rc;
 use std::sync::LazyLock;
 
 // Regex for embed links
 static EMBED_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(&format!(r"#(youtube|twitch|kick)/([a-zA-Z0-9]+)")).unwrap());
+    LazyLock::new(|| Regex::new(r"#(youtube|twitch|kick)/([a-zA-Z0-9]+)").unwrap());
+
+const GREENTEXT_COLOR: egui::Color32 = egui::Color32::from_rgb(0x6c, 0xa5, 0x28);
+const BROADCAST_COLOR: egui::Color32 = egui::Color32::from_rgb(0xed, 0xc3, 0x5a);
 
 static LINK_FINDER: LazyLock<LinkFinder> = LazyLock::new(|| {
     let mut link_finder = LinkFinder::new();
@@ -26,26 +33,52 @@ static LINK_FINDER: LazyLock<LinkFinder> = LazyLock::new(|| {
     link_finder
 });
 
+pub fn contains_link(text: &str) -> bool {
+    LINK_FINDER.links(text).next().is_some()
+}
+
 /// A single chat message.
 #[derive(Clone)]
 pub struct ChatMessageView {
+    /// The message's sequence number in the [MessageBuffer], which stays the same while older
+    /// messages are dropped.
+    ///
+    /// [MessageBuffer]: crate::gui::views::message_buffer::MessageBuffer
+    pub id: usize,
     pub username: String,
     pub username_color: Option<Rgba>,
     pub is_rainbow_color: bool,
     pub message: String,
     pub timestamp: String,
-    pub flair_images: Vec<Rc<RetainedImage>>,
+    pub sent_at: DateTime<Utc>,
+    pub flairs: Vec<(Rc<Flair>, Rc<AnimatedImage>)>,
+    /// The action of the filter rule matching this message, if any.
+    pub filter_action: Option<FilterAction>,
+    /// How the message is censored after its sender was muted or banned, if it is.
+    pub censor_style: Option<CensorStyle>,
+    /// Set for lines about chat events, such as bans, that weren't sent by `username`.
+    pub system_kind: Option<SystemMessageKind>,
+    /// The users who repeated this single-emote message right after it, forming a combo.
+    combo_nicks: Vec<String>,
     message_with_emotes: Vec<TextOrEmoteOrLink>,
+    is_expanded: Cell<bool>,
+    /// The width the message was last drawn at, and the height it took up.
+    measured_height: Cell<Option<(f32, f32)>>,
 }
 
 impl Debug for ChatMessageView {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ChatMessageView")
+            .field("id", &self.id)
             .field("username", &self.username)
             .field("username_color", &self.username_color)
             .field("is_rainbow_color", &self.is_rainbow_color)
             .field("message", &self.message)
             .field("timestamp", &self.timestamp)
+            .field("filter_action", &self.filter_action)
+            .field("system_kind", &self.system_kind)
+            .field("censor_style", &self.censor_style)
+            .field("combo_nicks", &self.combo_nicks)
             .finish()
     }
 }
@@ -56,26 +89,199 @@ impl ChatMessageView {
         username_color: Option<Rgba>,
         is_rainbow_color: bool,
         message: String,
-        timestamp: String,
-        flair_images: Vec<Rc<RetainedImage>>,
-        emote_images: &HashMap<String, Rc<RetainedImage>>,
+        sent_at: DateTime<Utc>,
+        flairs: Vec<(Rc<Flair>, Rc<AnimatedImage>)>,
+        emote_images: &HashMap<String, Rc<AnimatedImage>>,
     ) -> Self {
         let message_with_emotes = Self::parse_message(&message, emote_images);
 
         Self {
+            id: 0,
             username,
             username_color,
             is_rainbow_color,
             message,
-            timestamp,
-            flair_images,
+            timestamp: sent_at.format("%H:%M").to_string(),
+            sent_at,
+            flairs,
+            filter_action: None,
+            system_kind: None,
+            censor_style: None,
+            combo_nicks: Vec::new(),
             message_with_emotes,
+            is_expanded: Cell::new(false),
+            measured_height: Cell::new(None),
+        }
+    }
+
+    /// A line about a chat event rather than a message from a user, such as a broadcast.
+    pub fn system(
+        kind: SystemMessageKind,
+        text: String,
+        sent_at: DateTime<Utc>,
+        emote_images: &HashMap<String, Rc<AnimatedImage>>,
+    ) -> Self {
+        let mut view = Self::new(
+            String::new(),
+            None,
+            false,
+            text,
+            sent_at,
+            Vec::new(),
+            emote_images,
+        );
+        view.system_kind = Some(kind);
+        view
+    }
+
+    /// Parses the message again with a new set of emotes, e.g. once they have loaded.
+    pub fn set_emotes(&mut self, emote_images: &HashMap<String, Rc<AnimatedImage>>) {
+        self.message_with_emotes = Self::parse_message(&self.message, emote_images);
+    }
+
+    /// The height the message took up when last drawn, if that was at the same width.
+    pub fn height_at(&self, width: f32) -> Option<f32> {
+        self.measured_height
+            .get()
+            .and_then(|(measured_width, height)| (measured_width == width).then_some(height))
+    }
+
+    pub fn set_height(&self, width: f32, height: f32) {
+        self.measured_height.set(Some((width, height)));
+    }
+
+    /// A rough estimate of the memory used by the message, in bytes. Flair and emote images are
+    /// shared between messages, so they aren't counted.
+    pub fn estimated_size(&self) -> usize {
+        let strings = self.username.len()
+            + self.message.len()
+            + self.timestamp.len()
+            + self.combo_nicks.iter().map(String::len).sum::<usize>();
+        // The parsed tokens hold about another copy of the text.
+        let tokens = self.message_with_emotes.len() * std::mem::size_of::<TextOrEmoteOrLink>()
+            + self.message.len();
+        std::mem::size_of::<Self>() + strings + tokens
+    }
+
+    /// The emote prefix if the message is nothing but a single emote.
+    pub fn single_emote(&self) -> Option<&str> {
+        match self.message_with_emotes.as_slice() {
+            [TextOrEmoteOrLink::Emote(_)] => Some(self.message.trim()),
+            _ => None,
+        }
+    }
+
+    /// Counts the same emote sent by `nick` towards this message's combo. Returns `false` if
+    /// `message` doesn't continue the combo, and should be shown on its own, which includes a
+    /// user repeating the emote that they already counted towards it.
+    pub fn add_to_combo(&mut self, nick: &str, message: &str) -> bool {
+        if self.system_kind.is_some() || self.single_emote() != Some(message.trim()) {
+            return false;
+        }
+
+        let is_repeat = self.username.eq_ignore_ascii_case(nick)
+            || self
+                .combo_nicks
+                .iter()
+                .any(|n| n.eq_ignore_ascii_case(nick));
+        if is_repeat {
+            return false;
         }
+        self.combo_nicks.push(nick.to_string());
+        true
+    }
+
+    fn combo_count(&self) -> usize {
+        1 + self.combo_nicks.len()
+    }
+
+    /// Whether the message can be part of a group of messages from the same user, which it
+    /// can't while it is collapsed or a combo.
+    pub fn is_groupable(&self) -> bool {
+        let is_collapsed = (self.filter_action == Some(FilterAction::Collapse)
+            && !self.is_expanded.get())
+            || matches!(
+                self.active_censor(),
+                Some(CensorStyle::Collapse | CensorStyle::Blur)
+            );
+        !is_collapsed && self.combo_count() == 1 && self.system_kind.is_none()
+    }
+
+    /// The censor style to show the message with, unless it was clicked to reveal it.
+    fn active_censor(&self) -> Option<CensorStyle> {
+        self.censor_style
+            .filter(|style| *style != CensorStyle::Off && !self.is_expanded.get())
+    }
+
+    fn is_dimmed(&self) -> bool {
+        self.filter_action == Some(FilterAction::Dim)
     }
 
     fn parse_message(
         message: &str,
-        emotes: &HashMap<String, Rc<RetainedImage>>,
+        emotes: &HashMap<String, Rc<AnimatedImage>>,
+    ) -> Vec<TextOrEmoteOrLink> {
+        let link_tag = LinkTag::find(message);
+        let tokens = Self::parse_spans(message, emotes, link_tag);
+
+        if message.trim_start().starts_with('>') {
+            vec![TextOrEmoteOrLink::Greentext(tokens)]
+        } else {
+            tokens
+        }
+    }
+
+    /// Splits out `` `code` `` and `||spoiler||` spans, and parses the rest into words.
+    fn parse_spans(
+        text: &str,
+        emotes: &HashMap<String, Rc<AnimatedImage>>,
+        link_tag: Option<LinkTag>,
+    ) -> Vec<TextOrEmoteOrLink> {
+        let mut tokens = Vec::new();
+        let mut rest = text;
+
+        loop {
+            let code = rest.find('`').and_then(|start| {
+                let end = start + 1 + rest[start + 1..].find('`')?;
+                Some((start, end, 1))
+            });
+            let spoiler = rest.find("||").and_then(|start| {
+                let end = start + 2 + rest[start + 2..].find("||")?;
+                Some((start, end, 2))
+            });
+
+            let span = match (code, spoiler) {
+                (Some(code), Some(spoiler)) => {
+                    Some(if code.0 < spoiler.0 { code } else { spoiler })
+                }
+                (span, None) | (None, span) => span,
+            };
+            let Some((start, end, delimiter_len)) = span else {
+                tokens.extend(Self::parse_words(rest, emotes, link_tag));
+                break;
+            };
+
+            tokens.extend(Self::parse_words(&rest[..start], emotes, link_tag));
+            let inner = &rest[start + delimiter_len..end];
+            if delimiter_len == 1 {
+                tokens.push(TextOrEmoteOrLink::Code(inner.to_string()));
+            } else {
+                tokens.push(TextOrEmoteOrLink::Spoiler(Spoiler {
+                    text: inner.trim().to_string(),
+                    tokens: Self::parse_words(inner, emotes, link_tag),
+                    is_revealed: Cell::new(false),
+                }));
+            }
+            rest = &rest[end + delimiter_len..];
+        }
+
+        tokens
+    }
+
+    fn parse_words(
+        message: &str,
+        emotes: &HashMap<String, Rc<AnimatedImage>>,
+        link_tag: Option<LinkTag>,
     ) -> Vec<TextOrEmoteOrLink> {
         let mut last_index = 0;
 
@@ -85,13 +291,14 @@ impl ChatMessageView {
             if let Some(captures) = EMBED_REGEX.captures(word) {
                 let platform = captures.get(1).unwrap().as_str().to_ascii_lowercase();
                 let id = captures.get(2).unwrap().as_str().to_ascii_lowercase();
-                tokens.push(TextOrEmoteOrLink::EmbedLink(EmbedLink::new(
-                    word,
-                    platform.as_str(),
-                    id.as_str(),
-                )));
+                tokens.push(TextOrEmoteOrLink::EmbedLink(
+                    EmbedLink::new(word, platform.as_str(), id.as_str()),
+                    link_tag,
+                ));
             } else if let Some(emote) = emotes.get(word) {
                 tokens.push(TextOrEmoteOrLink::Emote(emote.clone()));
+            } else if let Some(emote) = ModifiedEmote::parse(word, emotes) {
+                tokens.push(TextOrEmoteOrLink::ModifiedEmote(emote));
             } else {
                 tokens.push(TextOrEmoteOrLink::Text(word.to_string()));
             }
@@ -108,7 +315,7 @@ impl ChatMessageView {
             }
 
             // Process the link
-            tokens.push(TextOrEmoteOrLink::Link(link.as_str().to_string()));
+            tokens.push(TextOrEmoteOrLink::Link(link.as_str().to_string(), link_tag));
             last_index = link.end();
         }
 
@@ -125,7 +332,7 @@ impl ChatMessageView {
 
     fn parse_message_with_emotes_no_links(
         message: &str,
-        emotes: &HashMap<String, Rc<RetainedImage>>,
+        emotes: &HashMap<String, Rc<AnimatedImage>>,
     ) -> Vec<TextOrEmoteOrLink> {
         let mut message_with_emotes = Vec::new();
         let mut push_text_or_emote = |current_word: String| {
@@ -158,8 +365,9 @@ impl ChatMessageView {
     }
 
     fn show_flairs(&self, ui: &mut Ui) {
-        for image in &self.flair_images {
-            ui.image(image.texture_id(ui.ctx()), egui::Vec2::new(16.0, 16.0));
+        for (flair, image) in &self.flairs {
+            ui.image(image.texture_id(ui.ctx()), image.display_size(ui, 1.0))
+                .on_hover_ui(|ui| show_flair_tooltip(ui, flair));
         }
     }
 
@@ -171,48 +379,385 @@ impl ChatMessageView {
                 let hue = i as f32 / len;
                 let color: Srgb = Srgb::from_color(Hsv::new(hue * 360.0, 1.0, 1.0));
 
-                let color = egui::Color32::from_rgb(
+                let mut color = egui::Color32::from_rgb(
                     (color.red * 255.0) as u8,
                     (color.green * 255.0) as u8,
                     (color.blue * 255.0) as u8,
                 );
+                if self.is_dimmed() {
+                    color = color.linear_multiply(0.5);
+                }
 
-                ui.colored_label(color, &c.to_string());
+                let response = ui.add(
+                    egui::Label::new(egui::RichText::new(c.to_string()).color(color))
+                        .sense(egui::Sense::click()),
+                );
+                self.show_username_context_menu(response);
             }
-        } else if let Some(color) = self.username_color {
-            ui.colored_label(color, &self.username);
         } else {
-            ui.label(&self.username);
+            let mut text = egui::RichText::new(&self.username);
+            if let Some(color) = self.username_color {
+                let mut color = egui::Color32::from(color);
+                if self.is_dimmed() {
+                    color = color.linear_multiply(0.5);
+                }
+                text = text.color(color);
+            }
+
+            let response = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+            self.show_username_context_menu(response);
+        }
+    }
+
+    fn show_username_context_menu(&self, response: Response) {
+        let response = response.on_hover_cursor(egui::CursorIcon::PointingHand);
+        if response.clicked() {
+            UiAction::OpenUserCard(self.username.clone()).push(&response.ctx);
         }
+
+        response.context_menu(|ui| {
+            if ui.button(format!("Ignore {}", self.username)).clicked() {
+                UiAction::Command(Command::Ignore(self.username.clone())).push(ui.ctx());
+                ui.close_menu();
+            }
+        });
     }
 
     fn show_message(&self, ui: &mut Ui) {
+        let is_struck = self.active_censor() == Some(CensorStyle::Strikethrough);
         for text_or_emote in &self.message_with_emotes {
-            text_or_emote.show(ui);
+            if is_struck {
+                text_or_emote.show_struck(ui);
+            } else {
+                text_or_emote.show(ui);
+            }
         }
     }
-}
 
-impl View for ChatMessageView {
-    fn show(&self, ui: &mut Ui) -> Response {
+    fn show_row(&self, ui: &mut Ui, is_continuation: bool) -> Response {
+        ui.horizontal_wrapped(|ui| {
+            if self.is_dimmed() {
+                ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+            }
+
+            if is_continuation {
+                ui.add_space(ui.spacing().indent);
+                ui.weak("›").on_hover_text(&self.timestamp);
+            } else {
+                ui.label(&self.timestamp);
+                ui.separator();
+                self.show_flairs(ui);
+                self.show_username(ui);
+                ui.separator();
+            }
+            self.show_message(ui);
+        })
+        .response
+    }
+
+    /// Shows the message, leaving out the timestamp, flairs and nick if it continues a group of
+    /// messages from the same user.
+    pub fn show_grouped(&self, ui: &mut Ui, is_continuation: bool) -> Response {
+        if let Some(kind) = self.system_kind {
+            return self.show_system(ui, kind);
+        }
+        if self.combo_count() > 1 {
+            return self.show_combo(ui);
+        }
+
+        match self.active_censor() {
+            Some(CensorStyle::Collapse) => {
+                return self.show_collapsed(ui, format!("Censored message from {}", self.username))
+            }
+            Some(CensorStyle::Blur) => return self.show_covered(ui),
+            Some(CensorStyle::Strikethrough) => {
+                let response = self
+                    .show_row(ui, is_continuation)
+                    .interact(egui::Sense::click())
+                    .on_hover_text("Click to reveal");
+                if response.clicked() {
+                    self.is_expanded.set(true);
+                }
+                return response;
+            }
+            _ => {}
+        }
+
+        match self.filter_action {
+            Some(FilterAction::Collapse) if !self.is_expanded.get() => {
+                self.show_collapsed(ui, format!("Collapsed message from {}", self.username))
+            }
+            Some(FilterAction::Highlight) => {
+                let fill = ui.visuals().selection.bg_fill.linear_multiply(0.4);
+                egui::Frame::none()
+                    .fill(fill)
+                    .show(ui, |ui| self.show_row(ui, is_continuation))
+                    .inner
+            }
+            _ => self.show_row(ui, is_continuation),
+        }
+    }
+
+    fn show_system(&self, ui: &mut Ui, kind: SystemMessageKind) -> Response {
+        let show_line = |ui: &mut Ui, color: egui::Color32| {
+            ui.horizontal_wrapped(|ui| {
+                ui.weak(&self.timestamp);
+                ui.separator();
+                ui.visuals_mut().override_text_color = Some(color);
+                self.show_message(ui);
+            })
+            .response
+        };
+
+        if kind == SystemMessageKind::Broadcast {
+            egui::Frame::none()
+                .fill(BROADCAST_COLOR.linear_multiply(0.15))
+                .stroke(egui::Stroke::new(1.0_f32, BROADCAST_COLOR))
+                .inner_margin(egui::Margin::same(4.0))
+                .show(ui, |ui| show_line(ui, BROADCAST_COLOR))
+                .inner
+        } else {
+            let color = ui.visuals().weak_text_color();
+            show_line(ui, color)
+        }
+    }
+
+    fn show_combo(&self, ui: &mut Ui) -> Response {
         ui.horizontal_wrapped(|ui| {
             ui.label(&self.timestamp);
             ui.separator();
-            self.show_flairs(ui);
-            self.show_username(ui);
-            ui.separator();
             self.show_message(ui);
+            let count = egui::RichText::new(format!("{}x", self.combo_count()))
+                .strong()
+                .size(ui.text_style_height(&egui::TextStyle::Heading));
+            ui.label(count);
+            ui.weak("C-C-C-COMBO");
         })
         .response
+        .on_hover_ui(|ui| {
+            ui.label(&self.username);
+            for nick in &self.combo_nicks {
+                ui.label(nick);
+            }
+        })
+    }
+
+    /// Shows the row with a cover over it that reveals it when clicked.
+    fn show_covered(&self, ui: &mut Ui) -> Response {
+        let row = self.show_row(ui, false);
+        let fill = ui.visuals().extreme_bg_color.gamma_multiply(0.95);
+        ui.painter().rect_filled(row.rect, 2.0, fill);
+
+        let response = ui
+            .interact(row.rect, row.id.with("cover"), egui::Sense::click())
+            .on_hover_text("Click to reveal");
+        if response.clicked() {
+            self.is_expanded.set(true);
+        }
+        response
+    }
+
+    fn show_collapsed(&self, ui: &mut Ui, text: String) -> Response {
+        ui.horizontal_wrapped(|ui| {
+            ui.weak(&self.timestamp);
+            ui.separator();
+            let label = egui::RichText::new(text).weak().italics();
+            if ui
+                .add(egui::Label::new(label).sense(egui::Sense::click()))
+                .on_hover_text("Click to expand")
+                .clicked()
+            {
+                self.is_expanded.set(true);
+            }
+        })
+        .response
+    }
+}
+
+/// Shows the label and description of a flair, e.g. when hovering over its image.
+pub fn show_flair_tooltip(ui: &mut Ui, flair: &Flair) {
+    ui.strong(&flair.label);
+    if let Some(description) = flair.description.as_ref().filter(|d| !d.is_empty()) {
+        ui.label(description);
+    }
+}
+
+impl View for ChatMessageView {
+    fn show(&self, ui: &mut Ui) -> Response {
+        self.show_grouped(ui, false)
     }
 }
 
 #[derive(Clone)]
 enum TextOrEmoteOrLink {
     Text(String),
-    Emote(Rc<RetainedImage>),
-    Link(String),
-    EmbedLink(EmbedLink),
+    Emote(Rc<AnimatedImage>),
+    ModifiedEmote(ModifiedEmote),
+    Link(String, Option<LinkTag>),
+    EmbedLink(EmbedLink, Option<LinkTag>),
+    /// A message starting with `>`.
+    Greentext(Vec<TextOrEmoteOrLink>),
+    /// A `||spoiler||`, hidden until clicked.
+    Spoiler(Spoiler),
+    /// A `` `code` `` span, shown verbatim.
+    Code(String),
+}
+
+#[derive(Clone)]
+struct Spoiler {
+    text: String,
+    tokens: Vec<TextOrEmoteOrLink>,
+    is_revealed: Cell<bool>,
+}
+
+impl Spoiler {
+    fn show(&self, ui: &mut Ui) -> Response {
+        if self.is_revealed.get() {
+            return show_tokens(ui, &self.tokens);
+        }
+
+        let fill = ui.visuals().extreme_bg_color;
+        let text = egui::RichText::new(&self.text)
+            .color(fill)
+            .background_color(fill);
+        let response = ui
+            .add(egui::Label::new(text).sense(egui::Sense::click()))
+            .on_hover_text("Click to reveal spoiler");
+        if response.clicked() {
+            self.is_revealed.set(true);
+        }
+        response
+    }
+}
+
+/// A warning about the links in a message, given by a tag word anywhere in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkTag {
+    Nsfl,
+    Nsfw,
+    Loud,
+    Weeb,
+}
+
+impl LinkTag {
+    /// The tag of a message, with the more severe tags taking precedence.
+    pub fn find(message: &str) -> Option<LinkTag> {
+        let words: Vec<String> = message
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_ascii_lowercase)
+            .collect();
+        let has = |tag: &str| words.iter().any(|w| w == tag);
+
+        [
+            ("nsfl", LinkTag::Nsfl),
+            ("nsfw", LinkTag::Nsfw),
+            ("loud", LinkTag::Loud),
+            ("weeb", LinkTag::Weeb),
+        ]
+        .into_iter()
+        .find(|(name, _)| has(name))
+        .map(|(_, tag)| tag)
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            LinkTag::Nsfl => "NSFL",
+            LinkTag::Nsfw => "NSFW",
+            LinkTag::Loud => "Loud",
+            LinkTag::Weeb => "Weeb",
+        }
+    }
+
+    fn color(&self) -> egui::Color32 {
+        match self {
+            LinkTag::Nsfl => egui::Color32::from_rgb(0xd6, 0xc5, 0x00),
+            LinkTag::Nsfw => egui::Color32::from_rgb(0xd0, 0x20, 0x20),
+            LinkTag::Loud => egui::Color32::from_rgb(0x3a, 0x9f, 0xe0),
+            LinkTag::Weeb => egui::Color32::from_rgb(0xb0, 0x6c, 0xe0),
+        }
+    }
+}
+
+/// Overrides the text color of `ui` while adding the contents, without starting a new layout
+/// that would break the wrapping of the message.
+fn with_text_color<R>(
+    ui: &mut Ui,
+    color: egui::Color32,
+    add_contents: impl FnOnce(&mut Ui) -> R,
+) -> R {
+    let previous_color = ui.visuals().override_text_color;
+    ui.visuals_mut().override_text_color = Some(color);
+    let result = add_contents(ui);
+    ui.visuals_mut().override_text_color = previous_color;
+    result
+}
+
+fn show_tokens(ui: &mut Ui, tokens: &[TextOrEmoteOrLink]) -> Response {
+    tokens
+        .iter()
+        .map(|token| token.show(ui))
+        .reduce(|a, b| a.union(b))
+        .unwrap_or_else(|| ui.label(""))
+}
+
+fn show_link(ui: &mut Ui, text: &str, url: &str, tag: Option<LinkTag>) -> Response {
+    match tag {
+        Some(tag) => ui
+            .hyperlink_to(egui::RichText::new(text).color(tag.color()), url)
+            .on_hover_text(format!("{} link", tag.label())),
+        None => ui.hyperlink_to(text, url),
+    }
+}
+
+/// An emote with modifiers, like `PEPE:wide:flip`.
+#[derive(Clone)]
+pub struct ModifiedEmote {
+    pub image: Rc<AnimatedImage>,
+    pub modifiers: Vec<EmoteModifier>,
+    pub original: String,
+}
+
+impl ModifiedEmote {
+    const SPIN_SECONDS: f64 = 2.0;
+
+    /// Returns `None` unless `word` is a known emote followed by known modifiers.
+    pub fn parse(word: &str, emotes: &HashMap<String, Rc<AnimatedImage>>) -> Option<Self> {
+        let (prefix, modifiers) = split_emote_modifiers(word)?;
+        Some(Self {
+            image: emotes.get(prefix)?.clone(),
+            modifiers,
+            original: word.to_string(),
+        })
+    }
+
+    fn has(&self, modifier: EmoteModifier) -> bool {
+        self.modifiers.contains(&modifier)
+    }
+
+    /// Shows the emote with the modifiers that can be drawn as a transformed image. The others
+    /// are only listed on hover.
+    fn show(&self, ui: &mut Ui) -> Response {
+        let mut size = self.image.display_size(ui, emote_scale(ui.ctx()));
+        let mut uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        if self.has(EmoteModifier::Mirror) {
+            std::mem::swap(&mut uv.min.x, &mut uv.max.x);
+        }
+        if self.has(EmoteModifier::Flip) {
+            std::mem::swap(&mut uv.min.y, &mut uv.max.y);
+        }
+        if self.has(EmoteModifier::Wide) {
+            size.x *= 2.0;
+        }
+
+        let mut image = egui::Image::new(self.image.texture_id(ui.ctx()), size).uv(uv);
+        if self.has(EmoteModifier::Spin) {
+            let turns = animate(ui.ctx()) / Self::SPIN_SECONDS;
+            let angle = (turns.fract() * std::f64::consts::TAU) as f32;
+            image = image.rotate(angle, egui::Vec2::splat(0.5));
+        }
+
+        ui.add(image).on_hover_text(&self.original)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -258,15 +803,134 @@ impl EmbedLink {
     }
 }
 
+impl TextOrEmoteOrLink {
+    /// Shows the token with its text struck through.
+    fn show_struck(&self, ui: &mut Ui) -> Response {
+        match self {
+            TextOrEmoteOrLink::Text(text) => ui.label(egui::RichText::new(text).strikethrough()),
+            TextOrEmoteOrLink::Code(code) => {
+                ui.label(egui::RichText::new(code).code().strikethrough())
+            }
+            TextOrEmoteOrLink::Greentext(tokens) => with_text_color(ui, GREENTEXT_COLOR, |ui| {
+                tokens
+                    .iter()
+                    .map(|token| token.show_struck(ui))
+                    .reduce(|a, b| a.union(b))
+                    .unwrap_or_else(|| ui.label(""))
+            }),
+            _ => self.show(ui),
+        }
+    }
+}
+
 impl View for TextOrEmoteOrLink {
     fn show(&self, ui: &mut Ui) -> Response {
         match self {
             TextOrEmoteOrLink::Text(text) => ui.label(text),
             TextOrEmoteOrLink::Emote(image) => {
-                ui.image(image.texture_id(ui.ctx()), egui::Vec2::new(16.0, 16.0))
+                let size = image.display_size(ui, emote_scale(ui.ctx()));
+                ui.image(image.texture_id(ui.ctx()), size)
+            }
+            TextOrEmoteOrLink::ModifiedEmote(emote) => emote.show(ui),
+            TextOrEmoteOrLink::Link(link, tag) => show_link(ui, link, link, *tag),
+            TextOrEmoteOrLink::EmbedLink(link, tag) => {
+                show_link(ui, link.value(), &link.url(), *tag)
+            }
+            TextOrEmoteOrLink::Greentext(tokens) => {
+                with_text_color(ui, GREENTEXT_COLOR, |ui| show_tokens(ui, tokens))
             }
-            TextOrEmoteOrLink::Link(link) => ui.hyperlink_to(link, link),
-            TextOrEmoteOrLink::EmbedLink(link) => ui.hyperlink_to(link.value(), link.url()),
+            TextOrEmoteOrLink::Spoiler(spoiler) => spoiler.show(ui),
+            TextOrEmoteOrLink::Code(code) => ui.code(code),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(message: &str) -> Vec<TextOrEmoteOrLink> {
+        ChatMessageView::parse_message(message, &HashMap::new())
+    }
+
+    #[test]
+    fn parses_code_and_spoiler_spans() {
+        let tokens = parse("see `a || b` and ||the ending|| now");
+
+        assert!(matches!(tokens.as_slice(), [
+            TextOrEmoteOrLink::Text(see),
+            TextOrEmoteOrLink::Code(code),
+            TextOrEmoteOrLink::Text(and),
+            TextOrEmoteOrLink::Spoiler(spoiler),
+            TextOrEmoteOrLink::Text(now),
+        ] if see == "see" && code == "a || b" && and == "and"
+            && spoiler.text == "the ending" && now == "now"));
+    }
+
+    #[test]
+    fn unclosed_spans_are_text() {
+        let tokens = parse("`a ||b");
+
+        assert!(matches!(tokens.as_slice(), [
+            TextOrEmoteOrLink::Text(a),
+            TextOrEmoteOrLink::Text(b),
+        ] if a == "`a" && b == "||b"));
+    }
+
+    #[test]
+    fn tags_links_and_greentext() {
+        let tokens = parse(">be me https://example.com nsfw");
+
+        let [TextOrEmoteOrLink::Greentext(tokens)] = tokens.as_slice() else {
+            panic!("Expected greentext");
+        };
+        assert!(tokens
+            .iter()
+            .any(|t| matches!(t, TextOrEmoteOrLink::Link(_, Some(LinkTag::Nsfw)))));
+        assert_eq!(LinkTag::find("NSFW and nsfl"), Some(LinkTag::Nsfl));
+        assert_eq!(LinkTag::find("nsfwish"), None);
+    }
+
+    #[test]
+    fn repeats_by_the_same_user_dont_continue_a_combo() {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(1, 1)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let emote = Rc::new(AnimatedImage::from_bytes("PEPE", png.get_ref()).unwrap());
+        let emotes = HashMap::from([("PEPE".to_string(), emote)]);
+        let mut view = ChatMessageView::new(
+            "Alice".to_string(),
+            None,
+            false,
+            "PEPE".to_string(),
+            Utc::now(),
+            Vec::new(),
+            &emotes,
+        );
+
+        assert!(!view.add_to_combo("alice", "PEPE"));
+        assert!(view.add_to_combo("Bob", "PEPE"));
+        assert!(!view.add_to_combo("bob", "PEPE"));
+        assert!(!view.add_to_combo("Carol", "hello"));
+        assert_eq!(view.combo_count(), 2);
+    }
+
+    #[test]
+    fn measured_height_is_only_kept_for_the_same_width() {
+        let view = ChatMessageView::new(
+            "Bob".to_string(),
+            None,
+            false,
+            "hello".to_string(),
+            Utc::now(),
+            Vec::new(),
+            &HashMap::new(),
+        );
+        assert_eq!(view.height_at(300.0), None);
+
+        view.set_height(300.0, 42.0);
+        assert_eq!(view.height_at(300.0), Some(42.0));
+        assert_eq!(view.height_at(250.0), None);
+    }
+}
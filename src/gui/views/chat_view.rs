@@ -1,109 +1,238 @@
 use anyhow::{anyhow, bail, Context, Result};
 
-use dgg::dgg::models::event::{ChatMessageData, EventData};
+use dgg::config::ChatAppConfig;
+use dgg::dgg::export::ExportRequest;
+use dgg::dgg::filter::{FilterAction, FilterInput, FilterRule};
+use dgg::dgg::models::event::{
+    BaseEventData, ChatMessageData, Event, EventData, ModerationData, PinData, WhisperData,
+};
 use dgg::dgg::models::flair::Flair;
+use dgg::dgg::models::image::Image;
+use dgg::dgg::models::user::User;
+use dgg::dgg::overrustle;
+use dgg::dgg::search::{SearchInput, SearchQuery};
+use dgg::settings::{CensorStyle, ChatAppSettings, SystemMessageKind};
 
+use crate::gui::animated_image::{advance_animations, set_emote_scale, AnimatedImage};
 use crate::gui::app_services::Command;
+use crate::gui::commands::format_duration;
+use crate::gui::ui_action::UiAction;
 use crate::gui::views::chat_input_view::ChatInputView;
-use crate::gui::views::chat_message_view::ChatMessageView;
+use crate::gui::views::chat_message_view::{contains_link, ChatMessageView};
+use crate::gui::views::export_view::ExportView;
+use crate::gui::views::import_view::{ImportView, LoadedLog};
+use crate::gui::views::message_buffer::MessageBuffer;
+use crate::gui::views::pin_banner_view::PinBannerView;
+use crate::gui::views::search_view::SearchView;
+use crate::gui::views::settings_view::SettingsView;
+use crate::gui::views::user_card_view::{UserCardInfo, UserCardView};
+use crate::gui::views::whisper_view::WhisperView;
 use crate::gui::{View, ViewMut};
 use cached::CachedAsync;
-use dgg::dgg::models::emote::Emote;
+use chrono::{DateTime, Utc};
+use dgg::dgg::models::emote::{split_emote_modifiers, Emote};
 use eframe::egui;
 use eframe::egui::panel::TopBottomSide::Bottom;
 use eframe::egui::{
-    Align, Layout, Response, Rgba, ScrollArea, TextBuffer, TextStyle, TopBottomPanel, Ui, Vec2,
-    Widget,
+    Align, Align2, Key, Layout, Modifiers, Order, Rect, Response, Rgba, ScrollArea, TextBuffer,
+    TextStyle, TopBottomPanel, Ui, Vec2, Widget,
 };
-use egui_extras::image::load_image_bytes;
-use egui_extras::RetainedImage;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::rc::Rc;
 use tokio::sync::mpsc::Sender;
-use url::quirks::username;
+
+/// An imported log, shown in place of the live chat until the user goes back to it.
+struct ChatLog {
+    name: String,
+    messages: MessageBuffer,
+}
 
 /// The main chat view, consisting of a list of [ChatMessageView]s and a [ChatInputView].
 #[derive(Default)]
 pub struct ChatView {
     chat_input_view: ChatInputView,
+    settings_view: SettingsView,
+    user_card: Option<UserCardView>,
+    pin_banner: PinBannerView,
+    whisper_view: WhisperView,
+    search_view: SearchView,
+    export_view: ExportView,
+    import_view: ImportView,
 
+    /// Whether the chat follows new messages, which it does until the user scrolls up.
     is_scrolled_to_bottom: bool,
-    messages: Vec<ChatMessageView>,
+    /// The number of messages pushed when the user was last at the bottom, to count the new
+    /// messages below.
+    seen_message_count: usize,
+    /// Set to jump back to the newest messages on the next frame.
+    scroll_to_bottom: bool,
+    /// The width the message rows were last drawn at.
+    row_width: f32,
+    current_user: Option<User>,
+    /// The latest details of every user seen, by lowercase nick.
+    users: HashMap<String, User>,
+    /// Lowercase nicks of the connected users.
+    connected_users: HashSet<String>,
+    messages: MessageBuffer,
+    /// Shown instead of `messages` while it's open.
+    log: Option<ChatLog>,
+    hidden_message_count: usize,
+    settings: ChatAppSettings,
     user_styles: HashMap<String, Option<UserStyle>>,
     default_username_color: Rgba,
     flairs: HashMap<String, Rc<Flair>>,
     emotes: HashMap<String, Rc<Emote>>,
 
-    flair_images: HashMap<String, Rc<RetainedImage>>,
-    emote_images: HashMap<String, Rc<RetainedImage>>,
+    flair_images: HashMap<String, Rc<AnimatedImage>>,
+    emote_images: HashMap<String, Rc<AnimatedImage>>,
 
     command_tx: Option<Sender<Command>>,
 }
 
 impl ChatView {
-    pub fn set_flairs(&mut self, flairs: HashMap<String, Flair>) -> Result<()> {
+    /// Loads the flair images, choosing the resolution that suits `pixels_per_point` best.
+    pub fn set_flairs(
+        &mut self,
+        flairs: HashMap<String, Flair>,
+        pixels_per_point: f32,
+    ) -> Result<()> {
         debug!("Updating {} flairs", flairs.len());
 
         self.flairs = flairs.into_iter().map(|(k, v)| (k, Rc::new(v))).collect();
         self.flair_images.clear();
 
         for flair in self.flairs.values() {
-            if flair.image.is_empty() {
-                continue;
-            }
-
-            let maybe_bytes = flair.image[0].bytes.as_ref();
-            if maybe_bytes.is_none() {
-                continue;
-            }
-
             let key = flair.name.clone();
-            let bytes = maybe_bytes.ok_or(anyhow!("Flair image has no bytes"))?;
-            let image = RetainedImage::from_color_image(
-                key.clone(),
-                load_image_bytes(bytes)
-                    .ok()
-                    .ok_or(anyhow!("Failed to load image"))?,
-            );
-
-            self.flair_images.insert(key, Rc::new(image));
+            if let Some(image) = load_image(&key, &flair.image, pixels_per_point)
+                .with_context(|| format!("Failed to load flair {}", key))?
+            {
+                self.flair_images.insert(key, Rc::new(image));
+            }
         }
         Ok(())
     }
 
-    pub fn set_emotes(&mut self, emotes: HashMap<String, Emote>) -> Result<()> {
+    /// Loads the emote images, choosing the resolution that suits `pixels_per_point` best.
+    pub fn set_emotes(
+        &mut self,
+        emotes: HashMap<String, Emote>,
+        pixels_per_point: f32,
+    ) -> Result<()> {
         debug!("Updating {} emotes", emotes.len());
 
         self.emotes = emotes.into_iter().map(|(k, v)| (k, Rc::new(v))).collect();
         self.emote_images.clear();
 
         for emote in self.emotes.values() {
-            if emote.image.is_empty() {
-                continue;
+            let key = emote.prefix.clone();
+            if let Some(image) = load_image(&key, &emote.image, pixels_per_point)
+                .with_context(|| format!("Failed to load emote {}", key))?
+            {
+                self.emote_images.insert(key, Rc::new(image));
             }
+        }
 
-            let maybe_bytes = emote.image[0].bytes.as_ref();
-            if maybe_bytes.is_none() {
-                continue;
-            }
+        self.messages
+            .update_each(|message| message.set_emotes(&self.emote_images));
+        if let Some(log) = self.log.as_mut() {
+            log.messages
+                .update_each(|message| message.set_emotes(&self.emote_images));
+        }
+        self.pin_banner.set_emotes(&self.emote_images);
+        self.chat_input_view
+            .tab_completer
+            .set_emotes(&self.emote_images);
+        self.chat_input_view
+            .emote_picker
+            .set_emotes(&self.emotes, &self.emote_images);
+        Ok(())
+    }
+}
 
-            let key = emote.prefix.clone();
-            let bytes = maybe_bytes.ok_or(anyhow!("Emote image has no bytes"))?;
-            let image = RetainedImage::from_color_image(
-                key.clone(),
-                load_image_bytes(bytes)
-                    .ok()
-                    .ok_or(anyhow!("Failed to load image"))?,
-            );
+/// Whether the rule highlights the messages of `nick`, as added by `/highlight`.
+fn is_highlight_of(rule: &FilterRule, nick: &str) -> bool {
+    rule.action == FilterAction::Highlight
+        && rule
+            .nick
+            .as_ref()
+            .is_some_and(|n| n.eq_ignore_ascii_case(nick))
+}
 
-            self.emote_images.insert(key, Rc::new(image));
+/// Sets the filter action of each message, in order, and restyles the censored ones.
+fn apply_filter_actions(
+    messages: &mut MessageBuffer,
+    actions: Vec<Option<FilterAction>>,
+    censor_style: CensorStyle,
+) {
+    let mut actions = actions.into_iter();
+    messages.update_each(|m| {
+        m.filter_action = actions.next().flatten();
+        if m.censor_style.is_some() {
+            m.censor_style = Some(censor_style);
         }
-        Ok(())
+    });
+}
+
+/// Describes a moderation event, e.g. `Bob was muted by Alice for 10m`.
+fn moderation_text(
+    kind: SystemMessageKind,
+    data: &ModerationData,
+    moderator: Option<&str>,
+) -> String {
+    let by = moderator
+        .map(|nick| format!(" by {}", nick))
+        .unwrap_or_default();
+    let duration = data
+        .duration
+        .filter(|d| *d > 0)
+        .map(|d| {
+            format!(
+                " for {}",
+                format_duration(std::time::Duration::from_secs(d))
+            )
+        })
+        .unwrap_or_default();
+
+    match kind {
+        SystemMessageKind::Mute => format!("{} was muted{}{}", data.data, by, duration),
+        SystemMessageKind::Unmute => format!("{} was unmuted{}", data.data, by),
+        SystemMessageKind::Ban if duration.is_empty() => {
+            format!("{} was permanently banned{}", data.data, by)
+        }
+        SystemMessageKind::Ban => format!("{} was banned{}{}", data.data, by, duration),
+        SystemMessageKind::Unban => format!("{} was unbanned{}", data.data, by),
+        SystemMessageKind::SubOnly if data.data == "on" => {
+            format!("Subscriber-only mode enabled{}", by)
+        }
+        SystemMessageKind::SubOnly => format!("Subscriber-only mode disabled{}", by),
+        SystemMessageKind::Broadcast => data.data.clone(),
     }
 }
 
+/// Loads the best of several resolutions of an image, sized like the smallest one. Returns
+/// `None` if none of them have been downloaded.
+fn load_image(
+    name: &str,
+    images: &[Image],
+    pixels_per_point: f32,
+) -> Result<Option<AnimatedImage>> {
+    let Some(native) = images.iter().min_by_key(|i| i.height) else {
+        return Ok(None);
+    };
+    let native_size = Vec2::new(native.width.into(), native.height.into());
+
+    let best = Image::best_for_height(images, native_size.y * pixels_per_point);
+    let Some(bytes) = best.and_then(|i| i.bytes.as_ref()) else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        AnimatedImage::from_bytes(name, bytes)?.with_size(native_size),
+    ))
+}
+
 #[derive(Debug, Default, Clone)]
 struct UserStyle {
     pub is_rainbow: bool,
@@ -116,7 +245,7 @@ impl UserStyle {
         let mut user_style = Self::default();
 
         if !flairs.is_empty() {
-            flairs.sort_by(|first, second| first.priority.cmp(&second.priority));
+            flairs.sort_by_key(|flair| flair.priority);
 
             for flair in flairs.iter().filter(|f| !f.hidden).cloned() {
                 if flair.rainbow_color {
@@ -146,53 +275,754 @@ impl UserStyle {
 }
 
 impl ChatView {
-    pub fn new(command_tx: Sender<Command>) -> Self {
+    pub fn new(command_tx: Sender<Command>, config: &ChatAppConfig) -> Self {
+        let cache_dir = config.get_cache_dir();
+
         Self {
+            settings: config.settings.clone(),
             default_username_color: Rgba::from_rgb(1.0, 1.0, 1.0),
             command_tx: Some(command_tx.clone()),
-            chat_input_view: ChatInputView::new(command_tx),
+            chat_input_view: ChatInputView::new(command_tx.clone(), cache_dir.as_deref()),
+            pin_banner: PinBannerView::new(cache_dir.as_ref().map(|dir| dir.join("pins.json"))),
+            whisper_view: WhisperView::new(
+                command_tx,
+                cache_dir.map(|dir| dir.join("whispers.json")),
+            ),
+            is_scrolled_to_bottom: true,
             ..Default::default()
         }
     }
 
     pub fn add_message(&mut self, msg: EventData<ChatMessageData>) -> Result<()> {
         let user = msg.base.user.context("Message has no user")?;
+        let user = self.note_sender(user);
+        let filter_action = if self.settings.ignore.hides(&user.nick, &msg.data.data) {
+            Some(FilterAction::Hide)
+        } else {
+            self.evaluate_filters(&user.nick, &user.features, &msg.data.data)
+        };
+
+        if filter_action == Some(FilterAction::Hide) {
+            trace!("Hiding message from {}", user.nick);
+            self.hidden_message_count += 1;
+        } else {
+            if !overrustle::is_from_log(&user) {
+                self.chat_input_view.tab_completer.note_active(&user.nick);
+            }
+
+            let is_last_shown = self.messages.back().is_some_and(|m| self.is_shown(m));
+            let is_combo = is_last_shown
+                && self
+                    .messages
+                    .update_last(|last| last.add_to_combo(&user.nick, &msg.data.data))
+                    == Some(true);
+            if is_combo {
+                trace!("Adding {} to combo", user.nick);
+                return Ok(());
+            }
+        }
+
+        let sent_at = msg.base.timestamp.context("Message has no timestamp")?;
+        let mut view = self.message_view(user, msg.data.data, sent_at)?;
+        view.filter_action = filter_action;
+
+        trace!("Adding message {:?}", view);
+        self.messages.push(view);
+
+        Ok(())
+    }
+
+    /// Remembers the sender of a message. Logs don't record features, so the sender of a logged
+    /// message gets the ones they were last seen with instead of replacing them.
+    fn note_sender(&mut self, mut user: User) -> User {
+        let key = user.nick.to_lowercase();
+        if !overrustle::is_from_log(&user) {
+            self.users.insert(key, user.clone());
+        } else if let Some(known) = self.users.get(&key) {
+            user.features = known.features.clone();
+        }
+        user
+    }
+
+    /// Builds the view of a message, styled by its sender's flairs.
+    fn message_view(
+        &mut self,
+        user: User,
+        text: String,
+        sent_at: DateTime<Utc>,
+    ) -> Result<ChatMessageView> {
         let user_style = self
             .get_user_style(user.nick.clone(), user.features)?
             .unwrap_or_default();
 
-        let timestamp = msg
-            .base
-            .timestamp
-            .context("Message has no timestamp")?
-            .format("%H:%M")
-            .to_string();
-
-        let flair_images = user_style
+        let flairs = user_style
             .flairs
             .iter()
             .filter(|f| !f.image.is_empty())
-            .map(|f| {
-                self.flair_images
-                    .get(f.name.as_str())
-                    .cloned()
-                    .expect(format!("Flair has no image data: {}.", f.name).as_str())
+            // A flair whose image didn't load is left out here, and still listed in the user card.
+            .filter_map(|f| {
+                let image = self.flair_images.get(f.name.as_str()).cloned();
+                if image.is_none() {
+                    trace!("Flair {} has no image", f.name);
+                }
+                Some((f.clone(), image?))
             })
-            .collect::<Vec<Rc<RetainedImage>>>();
+            .collect::<Vec<_>>();
 
-        let view = ChatMessageView::new(
+        Ok(ChatMessageView::new(
             user.nick,
             user_style.color,
             user_style.is_rainbow,
-            msg.data.data,
-            timestamp,
-            flair_images,
+            text,
+            sent_at,
+            flairs,
             &self.emote_images,
-        );
+        ))
+    }
 
-        trace!("Adding message {:?}", view);
+    pub fn add_broadcast(&mut self, broadcast: EventData<ChatMessageData>) {
+        let sent_at = broadcast.base.timestamp.unwrap_or_else(Utc::now);
+        self.add_system_message(SystemMessageKind::Broadcast, broadcast.data.data, sent_at);
+    }
+
+    /// Adds a line about a mute, ban or sub-only mode change of the given kind.
+    pub fn add_moderation(&mut self, kind: SystemMessageKind, event: EventData<ModerationData>) {
+        if matches!(kind, SystemMessageKind::Mute | SystemMessageKind::Ban) {
+            self.censor_messages(&event.data.data);
+        }
+
+        let text = moderation_text(kind, &event.data, event.base.nick());
+        let sent_at = event.base.timestamp.unwrap_or_else(Utc::now);
+        self.add_system_message(kind, text, sent_at);
+    }
+
+    /// Censors the messages of a user that was muted or banned, in the configured style.
+    fn censor_messages(&mut self, nick: &str) {
+        let style = self.settings.display.censor_style;
+        self.messages.update_each(|m| {
+            if m.system_kind.is_none() && m.username.eq_ignore_ascii_case(nick) {
+                m.censor_style = Some(style);
+            }
+        });
+    }
+
+    fn add_system_message(
+        &mut self,
+        kind: SystemMessageKind,
+        text: String,
+        sent_at: DateTime<Utc>,
+    ) {
+        let view = ChatMessageView::system(kind, text, sent_at, &self.emote_images);
+        trace!("Adding system message {:?}", view);
         self.messages.push(view);
+    }
+
+    /// Shows a new pinned message, or removes the current one.
+    pub fn set_pin(&mut self, pin: EventData<PinData>) -> Result<()> {
+        if pin.data.is_unpin() {
+            debug!("Removing pin");
+            self.pin_banner.set_pin(pin.data.uuid, None);
+            return Ok(());
+        }
 
+        let Some(user) = pin.base.user else {
+            warn!("Skipping pin {} without a user", pin.data.uuid);
+            return Ok(());
+        };
+        let sent_at = pin.base.timestamp.unwrap_or_else(Utc::now);
+        let view = self.message_view(user, pin.data.data, sent_at)?;
+        self.pin_banner.set_pin(pin.data.uuid, Some(view));
+        Ok(())
+    }
+
+    /// Ids of the messages matching the search, oldest first.
+    fn search_matches(&self, query: &SearchQuery) -> Vec<usize> {
+        self.shown_messages()
+            .iter()
+            .filter(|m| self.is_shown(m))
+            .filter(|m| {
+                let features = self
+                    .users
+                    .get(&m.username.to_lowercase())
+                    .map(|u| u.features.as_slice())
+                    .unwrap_or_default();
+                query.matches(&SearchInput {
+                    nick: &m.username,
+                    features,
+                    text: &m.message,
+                    has_link: contains_link(&m.message),
+                    sent_at: m.sent_at,
+                })
+            })
+            .map(|m| m.id)
+            .collect()
+    }
+
+    fn evaluate_filters(
+        &self,
+        nick: &str,
+        features: &[String],
+        text: &str,
+    ) -> Option<FilterAction> {
+        self.settings.filters.evaluate(&FilterInput {
+            nick,
+            features,
+            text,
+            has_link: contains_link(text),
+            is_emote_only: self.is_emote_only(text),
+        })
+    }
+
+    /// Applies the current ignore list and filters to the messages already received, after
+    /// either changed. Hidden messages are kept, so that they show again if that changes back.
+    fn reapply_filters(&mut self) {
+        let censor_style = self.settings.display.censor_style;
+        let actions = self.filter_actions(&self.messages);
+        apply_filter_actions(&mut self.messages, actions, censor_style);
+        if let Some(mut log) = self.log.take() {
+            let actions = self.filter_actions(&log.messages);
+            apply_filter_actions(&mut log.messages, actions, censor_style);
+            self.log = Some(log);
+        }
+        self.count_hidden_messages();
+    }
+
+    fn filter_actions(&self, messages: &MessageBuffer) -> Vec<Option<FilterAction>> {
+        messages
+            .iter()
+            .map(|m| {
+                if m.system_kind.is_some() {
+                    return None;
+                }
+                if self.settings.ignore.hides(&m.username, &m.message) {
+                    return Some(FilterAction::Hide);
+                }
+
+                let features = self
+                    .users
+                    .get(&m.username.to_lowercase())
+                    .map(|u| u.features.as_slice())
+                    .unwrap_or_default();
+                self.evaluate_filters(&m.username, features, &m.message)
+            })
+            .collect()
+    }
+
+    fn count_hidden_messages(&mut self) {
+        self.hidden_message_count = self
+            .messages
+            .iter()
+            .filter(|m| m.filter_action == Some(FilterAction::Hide))
+            .count();
+    }
+
+    /// Shows a button over the bottom of the messages that jumps down to the new messages
+    /// received since the user scrolled up, if there are any.
+    fn show_new_messages_button(&mut self, ctx: &egui::Context, rect: Rect) {
+        let count = self.messages.pushed_count() - self.seen_message_count;
+        if count == 0 {
+            return;
+        }
+
+        let text = match count {
+            1 => "1 new message ↓".to_string(),
+            count => format!("{} new messages ↓", count),
+        };
+        egui::Area::new("new_messages_button")
+            .order(Order::Foreground)
+            .fixed_pos(rect.center_bottom() - Vec2::new(0.0, 8.0))
+            .pivot(Align2::CENTER_BOTTOM)
+            .show(ctx, |ui| {
+                if ui.button(text).clicked() {
+                    self.scroll_to_bottom = true;
+                }
+            });
+    }
+
+    /// The height of each message row including the spacing after it, at `width`. Rows that
+    /// haven't been drawn at this width yet are assumed to take up one line.
+    fn row_heights(&self, width: f32, row_height: f32, ui: &Ui) -> Vec<f32> {
+        let spacing = ui.spacing().item_spacing.y;
+        self.shown_messages()
+            .iter()
+            .map(|m| {
+                if self.is_shown(m) {
+                    m.height_at(width).unwrap_or(row_height) + spacing
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the message is drawn, which it isn't if the ignore list or a filter hides it, or
+    /// if it's a system line whose kind is turned off in the settings. Such messages are kept,
+    /// so that they show again once the settings change.
+    fn is_shown(&self, message: &ChatMessageView) -> bool {
+        message.filter_action != Some(FilterAction::Hide)
+            && message
+                .system_kind
+                .is_none_or(|kind| self.settings.system_messages.shows(kind))
+    }
+
+    /// The messages drawn and searched, which are those of the open log if there is one.
+    fn shown_messages(&self) -> &MessageBuffer {
+        self.log
+            .as_ref()
+            .map_or(&self.messages, |log| &log.messages)
+    }
+
+    /// Draws the message rows inside the `viewport` of the scroll area, measuring each one so
+    /// that the rows above and below it can be skipped. Returns the width of the rows.
+    fn show_rows(&self, ui: &mut Ui, viewport: Rect, row_height: f32) -> f32 {
+        let width = ui.available_width();
+        let spacing = ui.spacing().item_spacing.y;
+        let heights = self.row_heights(width, row_height, ui);
+        ui.set_height(heights.iter().sum());
+
+        let origin = ui.min_rect().min;
+        let mut top = 0.0;
+        let mut row = 0;
+        while row < heights.len() && top + heights[row] < viewport.min.y {
+            top += heights[row];
+            row += 1;
+        }
+
+        let mut is_changed = false;
+        while row < heights.len() && top < viewport.max.y {
+            if heights[row] == 0.0 {
+                row += 1;
+                continue;
+            }
+
+            let rect = Rect::from_min_size(origin + Vec2::new(0.0, top), Vec2::new(width, 0.0));
+            let height = ui
+                .allocate_ui_at_rect(rect, |ui| self.show_row(ui, row))
+                .response
+                .rect
+                .height();
+
+            let message = &self.shown_messages()[row];
+            if message.height_at(width) != Some(height) {
+                message.set_height(width, height);
+                is_changed = true;
+            }
+            top += height + spacing;
+            row += 1;
+        }
+
+        // Lay out again with the new heights, so that the rows don't overlap.
+        if is_changed {
+            ui.ctx().request_repaint();
+        }
+        width
+    }
+
+    fn show_row(&self, ui: &mut Ui, row: usize) {
+        let is_continuation = self.continues_group(row);
+        let message = &self.shown_messages()[row];
+        match self.search_view.match_state(message.id) {
+            Some(is_current) => {
+                let alpha = if is_current { 0.6 } else { 0.25 };
+                let fill = ui.visuals().selection.bg_fill.linear_multiply(alpha);
+                egui::Frame::none()
+                    .fill(fill)
+                    .show(ui, |ui| message.show_grouped(ui, is_continuation));
+            }
+            None => {
+                message.show_grouped(ui, is_continuation);
+            }
+        }
+    }
+
+    /// Whether the message at `index` continues a group of messages from the same user, which
+    /// is checked when drawing so that it holds regardless of which messages are hidden.
+    fn continues_group(&self, index: usize) -> bool {
+        const GROUP_WINDOW_SECONDS: i64 = 60;
+
+        if !self.settings.display.group_messages {
+            return false;
+        }
+
+        let messages = self.shown_messages();
+        let message = &messages[index];
+        let Some(previous) = messages.range(..index).rev().find(|m| self.is_shown(m)) else {
+            return false;
+        };
+        previous.username.eq_ignore_ascii_case(&message.username)
+            && (message.sent_at - previous.sent_at).num_seconds() <= GROUP_WINDOW_SECONDS
+            && previous.is_groupable()
+            && message.is_groupable()
+    }
+
+    /// Sets the user we are logged in as, or `None` if anonymous.
+    pub fn set_current_user(&mut self, user: Option<User>) {
+        self.chat_input_view
+            .emote_picker
+            .set_sub_tier(user.as_ref().map_or(0, User::sub_tier));
+        self.current_user = user;
+    }
+
+    /// Drops the oldest messages beyond the configured limits, and forgets the users that
+    /// neither sent one of the remaining messages nor are connected.
+    fn evict_messages(&mut self) {
+        let display = &self.settings.display;
+        let evicted = self
+            .messages
+            .evict(display.max_messages, display.max_message_memory_mb << 20);
+        if evicted == 0 {
+            return;
+        }
+        trace!("Dropped {} old messages", evicted);
+        self.count_hidden_messages();
+
+        // Only prune once there are plenty of stale users, as it goes over every message.
+        if self.users.len() <= 2 * (self.connected_users.len() + self.messages.len()) {
+            return;
+        }
+        let senders: HashSet<_> = self
+            .messages
+            .iter()
+            .filter(|m| m.system_kind.is_none())
+            .map(|m| m.username.to_lowercase())
+            .collect();
+        self.users
+            .retain(|nick, _| self.connected_users.contains(nick) || senders.contains(nick));
+        debug!("Keeping the details of {} users", self.users.len());
+    }
+
+    pub fn set_connected_users(&mut self, users: Vec<User>) {
+        debug!("{} users connected", users.len());
+        self.connected_users = users.iter().map(|u| u.nick.to_lowercase()).collect();
+        self.chat_input_view
+            .tab_completer
+            .set_connected(users.iter().map(|u| u.nick.clone()));
+        self.users
+            .extend(users.into_iter().map(|u| (u.nick.to_lowercase(), u)));
+    }
+
+    pub fn add_connected_user(&mut self, user: User) {
+        self.connected_users.insert(user.nick.to_lowercase());
+        self.chat_input_view.tab_completer.add_connected(&user.nick);
+        self.users.insert(user.nick.to_lowercase(), user);
+    }
+
+    pub fn remove_connected_user(&mut self, user: User) {
+        self.connected_users.remove(&user.nick.to_lowercase());
+        self.chat_input_view
+            .tab_completer
+            .remove_connected(&user.nick);
+    }
+
+    fn user_card_info(&self, nick: &str) -> UserCardInfo<'_> {
+        const MAX_RECENT_MESSAGES: usize = 20;
+
+        let key = nick.to_lowercase();
+        let user = self.users.get(&key);
+
+        let mut flairs: Vec<_> = user
+            .map(|u| u.features.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|feature| self.flairs.get(feature))
+            .map(|flair| (flair.clone(), self.flair_images.get(&flair.name).cloned()))
+            .collect();
+        flairs.sort_by_key(|(flair, _)| flair.priority);
+
+        let mut recent_messages: Vec<_> = self
+            .messages
+            .iter()
+            .rev()
+            .filter(|m| m.username.eq_ignore_ascii_case(nick) && self.is_shown(m))
+            .take(MAX_RECENT_MESSAGES)
+            .map(|m| (m.timestamp.as_str(), m.message.as_str()))
+            .collect();
+        recent_messages.reverse();
+
+        UserCardInfo {
+            user,
+            flairs,
+            is_connected: self.connected_users.contains(&key),
+            recent_messages,
+            can_moderate: self.current_user.as_ref().is_some_and(User::is_moderator),
+        }
+    }
+
+    fn show_user_card(&mut self, ctx: &egui::Context) {
+        let Some(mut user_card) = self.user_card.take() else {
+            return;
+        };
+
+        let is_open = {
+            let info = self.user_card_info(&user_card.nick);
+            user_card.show(ctx, &info)
+        };
+        if is_open {
+            self.user_card = Some(user_card);
+        }
+    }
+
+    fn is_emote_only(&self, text: &str) -> bool {
+        let mut words = text.split_whitespace().peekable();
+        words.peek().is_some()
+            && words.all(|word| {
+                let prefix = split_emote_modifiers(word).map_or(word, |(prefix, _)| prefix);
+                self.emote_images.contains_key(prefix)
+            })
+    }
+
+    pub fn open_settings(&mut self) {
+        self.settings_view.is_open = true;
+    }
+
+    pub fn open_export(&mut self) {
+        self.export_view.is_open = true;
+    }
+
+    /// Shows the export window, and starts an export with the current filters when asked.
+    fn show_export(&mut self, ctx: &egui::Context) {
+        let Some(options) = self.export_view.show(ctx) else {
+            return;
+        };
+
+        // The largest images needed for emotes drawn at about twice the text height.
+        let emotes = self
+            .emotes
+            .values()
+            .filter_map(|emote| {
+                let image = Image::best_for_height(&emote.image, 64.0)?;
+                Some((emote.prefix.clone(), image.clone()))
+            })
+            .collect();
+        let request = ExportRequest {
+            path: options.path,
+            format: options.format,
+            after: options.after,
+            before: options.before,
+            filters: self.settings.filters.clone(),
+            ignore: self.settings.ignore.clone(),
+            emotes,
+        };
+
+        if let Some(command_tx) = self.command_tx.as_ref() {
+            command_tx
+                .blocking_send(Command::Export {
+                    request: Box::new(request),
+                    status: options.status,
+                })
+                .expect("Failed to send command");
+        }
+    }
+
+    pub fn open_import(&mut self) {
+        self.import_view.is_open = true;
+    }
+
+    fn show_import(&mut self, ctx: &egui::Context) {
+        if let Some(log) = self.import_view.take_log() {
+            self.open_log(log);
+        }
+        let Some(options) = self.import_view.show(ctx) else {
+            return;
+        };
+
+        if let Some(command_tx) = self.command_tx.as_ref() {
+            command_tx
+                .blocking_send(Command::ImportLog {
+                    path: options.path,
+                    save_to_history: options.save_to_history,
+                    log: options.log,
+                    status: options.status,
+                })
+                .expect("Failed to send command");
+        }
+    }
+
+    /// Shows a loaded log in place of the live chat. Its messages are filtered and styled like
+    /// live ones, but don't change what is known about their senders.
+    fn open_log(&mut self, log: LoadedLog) {
+        let live_messages = std::mem::take(&mut self.messages);
+        let hidden_message_count = self.hidden_message_count;
+        for event in log.events {
+            if let Event::ChatMessage(message) = event {
+                self.add_message(message).unwrap_or_else(|e| {
+                    warn!("Skipping logged message: {:?}", e);
+                });
+            }
+        }
+        let messages = std::mem::replace(&mut self.messages, live_messages);
+        self.hidden_message_count = hidden_message_count;
+
+        self.log = Some(ChatLog {
+            name: log.name,
+            messages,
+        });
+        self.search_view.search_again();
+    }
+
+    fn close_log(&mut self) {
+        self.log = None;
+        self.search_view.search_again();
+    }
+
+    /// Shows which log is open in place of the live chat, with a button to go back.
+    fn show_log_banner(&mut self, ui: &mut Ui) {
+        let Some(log) = self.log.as_ref() else {
+            return;
+        };
+        let mut is_closed = false;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Log of {}, {} messages",
+                log.name,
+                log.messages.len()
+            ));
+            is_closed = ui.button("Back to live chat").clicked();
+        });
+        if is_closed {
+            self.close_log();
+        }
+    }
+
+    pub fn open_whispers(&mut self) {
+        self.whisper_view.is_open = true;
+    }
+
+    pub fn unread_whisper_count(&self) -> usize {
+        self.whisper_view.unread_count()
+    }
+
+    pub fn add_whisper(&mut self, whisper: EventData<WhisperData>) -> Result<()> {
+        let nick = whisper.data.nick;
+        if self.settings.ignore.is_ignored(&nick) {
+            trace!("Hiding whisper from {}", nick);
+            return Ok(());
+        }
+
+        let sent_at = whisper.base.timestamp.unwrap_or_else(Utc::now);
+        self.whisper_view
+            .add_incoming(&nick, whisper.data.data, sent_at);
+        Ok(())
+    }
+
+    pub fn ignore_user(&mut self, nick: &str) -> Result<()> {
+        if self.settings.ignore.ignore(nick) {
+            info!("Ignoring {}", nick);
+            self.settings.save()?;
+            self.reapply_filters();
+        }
+        Ok(())
+    }
+
+    pub fn unignore_user(&mut self, nick: &str) -> Result<()> {
+        if self.settings.ignore.unignore(nick) {
+            info!("No longer ignoring {}", nick);
+            self.settings.save()?;
+            self.reapply_filters();
+        }
+        Ok(())
+    }
+
+    /// Shows the ignore list and its options, for use in a menu.
+    pub fn show_ignore_menu(&mut self, ui: &mut Ui) {
+        if ui
+            .checkbox(
+                &mut self.settings.ignore.hide_mentions,
+                "Hide messages mentioning ignored users",
+            )
+            .changed()
+        {
+            self.reapply_filters();
+            self.settings.save().unwrap_or_else(|e| {
+                error!("Failed to save settings: {:?}", e);
+            });
+        }
+
+        ui.separator();
+        if self.settings.ignore.users.is_empty() {
+            ui.weak("No ignored users");
+        }
+
+        for nick in &self.settings.ignore.users {
+            ui.horizontal(|ui| {
+                ui.label(nick);
+                if ui.small_button("Unignore").clicked() {
+                    UiAction::Command(Command::Unignore(nick.clone())).push(ui.ctx());
+                }
+            });
+        }
+    }
+
+    pub fn highlight_user(&mut self, nick: &str) -> Result<()> {
+        let rules = &self.settings.filters.rules;
+        if rules.iter().any(|rule| is_highlight_of(rule, nick)) {
+            return Ok(());
+        }
+
+        let mut rule = FilterRule::new(format!("Highlight {}", nick), FilterAction::Highlight);
+        rule.nick = Some(nick.to_string());
+        self.settings.filters.rules.push(rule);
+        self.settings.save()?;
+
+        self.messages.update_each(|m| {
+            if m.username.eq_ignore_ascii_case(nick) {
+                m.filter_action = Some(FilterAction::Highlight);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn unhighlight_user(&mut self, nick: &str) -> Result<()> {
+        self.settings
+            .filters
+            .rules
+            .retain(|rule| !is_highlight_of(rule, nick));
+        self.settings.save()?;
+
+        self.messages.update_each(|m| {
+            if m.username.eq_ignore_ascii_case(nick)
+                && m.filter_action == Some(FilterAction::Highlight)
+            {
+                m.filter_action = None;
+            }
+        });
+        Ok(())
+    }
+
+    /// Runs a command that only affects the UI, and forwards any other to the services.
+    pub fn run_command(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Ignore(nick) => self.ignore_user(&nick)?,
+            Command::Unignore(nick) => self.unignore_user(&nick)?,
+            Command::Highlight(nick) => self.highlight_user(&nick)?,
+            Command::Unhighlight(nick) => self.unhighlight_user(&nick)?,
+            Command::Clear => {
+                self.messages.clear();
+                self.hidden_message_count = 0;
+                self.seen_message_count = self.messages.pushed_count();
+            }
+            Command::Help => {}
+            command => {
+                if let Some(command_tx) = self.command_tx.as_ref() {
+                    command_tx.blocking_send(command)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_ui_actions(&mut self, ui: &Ui) -> Result<()> {
+        for action in UiAction::take_all(ui.ctx()) {
+            match action {
+                UiAction::Command(command) => self.run_command(command)?,
+                UiAction::OpenUserCard(nick) => self.user_card = Some(UserCardView::new(nick)),
+                UiAction::Mention(nick) => self.chat_input_view.insert_word(nick),
+                UiAction::StartWhisper(nick) => self.whisper_view.open(&nick),
+                UiAction::WhisperSent { nick, message } => {
+                    self.whisper_view.add_outgoing(&nick, message)
+                }
+            }
+        }
         Ok(())
     }
 
@@ -206,12 +1036,7 @@ impl ChatView {
             None => {
                 let mut flairs = flairs
                     .into_iter()
-                    .map(|f| {
-                        self.flairs
-                            .get(&f)
-                            .context("Flair not found")
-                            .map(|f| f.clone())
-                    })
+                    .map(|f| self.flairs.get(&f).context("Flair not found").cloned())
                     .filter_map(|f| f.ok())
                     .collect::<Vec<Rc<Flair>>>();
 
@@ -223,18 +1048,85 @@ impl ChatView {
 
 impl ViewMut for ChatView {
     fn show(&mut self, ui: &mut Ui) -> Response {
+        self.handle_ui_actions(ui).unwrap_or_else(|e| {
+            error!("Failed to handle UI action: {:?}", e);
+        });
+
+        let is_unfocused = !ui.input(|i| i.focused);
+        advance_animations(
+            ui.ctx(),
+            self.settings.display.pause_animations_when_unfocused && is_unfocused,
+        );
+        set_emote_scale(ui.ctx(), self.settings.display.emote_scale);
+
+        if self.settings_view.show(ui.ctx(), &mut self.settings) {
+            self.reapply_filters();
+        }
+        if self.settings_view.should_save(ui.ctx()) {
+            self.settings.save().unwrap_or_else(|e| {
+                error!("Failed to save settings: {:?}", e);
+            });
+        }
+        self.show_user_card(ui.ctx());
+        self.whisper_view.show(ui.ctx());
+        self.show_export(ui.ctx());
+        self.show_import(ui.ctx());
+
         ui.with_layout(Layout::top_down(Align::Max), |ui| {
             ui.vertical(|ui| {
-                ScrollArea::new([false, true]).show_rows(
-                    ui,
-                    ui.text_style_height(&TextStyle::Body),
-                    self.messages.len(),
-                    |ui, rows| {
-                        for row in rows.start..rows.end {
-                            self.messages[row].show(ui);
-                        }
-                    },
-                );
+                self.pin_banner.show(ui);
+                self.show_log_banner(ui);
+
+                if self.hidden_message_count > 0 {
+                    ui.weak(format!("{} hidden messages", self.hidden_message_count));
+                }
+
+                if ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::F)) {
+                    self.search_view.open();
+                }
+                // Keep older messages while the user is reading them.
+                if self.is_scrolled_to_bottom {
+                    self.evict_messages();
+                }
+
+                self.search_view.show(ui);
+                let generation = self.shown_messages().generation();
+                if let Some(query) = self.search_view.outdated_query(generation) {
+                    let matches = self.search_matches(&query);
+                    self.search_view.set_matches(matches);
+                }
+
+                let row_height = ui.text_style_height(&TextStyle::Body);
+                let is_live = self.log.is_none();
+                let mut scroll_area = ScrollArea::new([false, true])
+                    .id_source(if is_live { "chat" } else { "chat_log" })
+                    .stick_to_bottom(true);
+                if std::mem::take(&mut self.scroll_to_bottom) {
+                    let heights = self.row_heights(self.row_width, row_height, ui);
+                    let bottom = heights.iter().sum::<f32>() - ui.available_height();
+                    scroll_area = scroll_area.vertical_scroll_offset(bottom.max(0.0));
+                }
+                let target = self.search_view.take_scroll_target();
+                if let Some(target) = target.and_then(|id| self.shown_messages().position(id)) {
+                    let heights = self.row_heights(self.row_width, row_height, ui);
+                    let top: f32 = heights.iter().take(target).sum();
+                    let offset = top - ui.available_height() / 2.0;
+                    scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+                }
+
+                let output = scroll_area
+                    .show_viewport(ui, |ui, viewport| self.show_rows(ui, viewport, row_height));
+                self.row_width = output.inner;
+                if !is_live {
+                    return;
+                }
+                let max_offset = output.content_size.y - output.inner_rect.height();
+                self.is_scrolled_to_bottom = output.state.offset.y >= max_offset - 1.0;
+                if self.is_scrolled_to_bottom {
+                    self.seen_message_count = self.messages.pushed_count();
+                } else {
+                    self.show_new_messages_button(ui.ctx(), output.inner_rect);
+                }
             });
 
             ui.allocate_space(Vec2::from([
@@ -253,3 +1145,106 @@ impl ViewMut for ChatView {
         .response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_moderation_events() {
+        let data = |data: &str, duration| ModerationData {
+            data: data.to_string(),
+            duration,
+        };
+
+        assert_eq!(
+            moderation_text(
+                SystemMessageKind::Mute,
+                &data("Bob", Some(600)),
+                Some("Alice")
+            ),
+            "Bob was muted by Alice for 10m"
+        );
+        assert_eq!(
+            moderation_text(SystemMessageKind::Ban, &data("Bob", None), None),
+            "Bob was permanently banned"
+        );
+        assert_eq!(
+            moderation_text(SystemMessageKind::SubOnly, &data("on", None), Some("Alice")),
+            "Subscriber-only mode enabled by Alice"
+        );
+    }
+
+    #[test]
+    fn unignoring_shows_the_hidden_messages_again() -> Result<()> {
+        let mut view = ChatView::default();
+        view.settings.ignore.ignore("bob");
+        for line in [
+            "[2023-06-25 05:34:02 UTC] Bob: hi",
+            "[2023-06-25 05:34:03 UTC] Alice: hello",
+        ] {
+            let Some(Event::ChatMessage(message)) = overrustle::parse_line(line) else {
+                panic!("Expected a chat message");
+            };
+            view.add_message(message)?;
+        }
+        assert_eq!(view.messages.len(), 2);
+        assert_eq!(view.hidden_message_count, 1);
+        assert!(!view.is_shown(&view.messages[0]));
+
+        view.settings.ignore.unignore("bob");
+        view.reapply_filters();
+        assert_eq!(view.hidden_message_count, 0);
+        assert!(view.messages.iter().all(|m| view.is_shown(m)));
+        Ok(())
+    }
+
+    #[test]
+    fn logs_are_shown_apart_from_the_live_chat() -> Result<()> {
+        let mut view = ChatView::default();
+        let Some(Event::ChatMessage(live)) =
+            overrustle::parse_line("[2023-06-26 10:00:00 UTC] Alice: live")
+        else {
+            panic!("Expected a chat message");
+        };
+        view.add_message(live)?;
+
+        view.open_log(LoadedLog {
+            name: "2023-06-25".to_string(),
+            events: overrustle::parse_log(
+                "[2023-06-25 05:34:02 UTC] Bob: hi\n[2023-06-25 05:34:03 UTC] Bob: there",
+            ),
+        });
+        assert_eq!(view.messages.len(), 1);
+        assert_eq!(view.shown_messages().len(), 2);
+
+        view.close_log();
+        assert_eq!(view.shown_messages()[0].message, "live");
+        Ok(())
+    }
+
+    #[test]
+    fn logged_messages_use_the_known_features_of_their_sender() -> Result<()> {
+        let mut view = ChatView::default();
+        let live = Event::try_from(
+            r#"MSG {"id":5,"nick":"Bob","features":["flair3"],"createdDate":"2021-11-17T20:20:01Z","timestamp":1000,"data":"hi"}"#,
+        )?;
+        let Event::ChatMessage(live) = live else {
+            panic!("Expected a chat message");
+        };
+        view.add_message(live)?;
+
+        let Some(Event::ChatMessage(logged)) =
+            overrustle::parse_line("[2023-06-25 05:34:02 UTC] bob: hello")
+        else {
+            panic!("Expected a chat message");
+        };
+        view.add_message(logged)?;
+
+        let bob = &view.users["bob"];
+        assert_eq!(bob.id, 5);
+        assert_eq!(bob.features, vec!["flair3".to_string()]);
+        assert_eq!(view.messages.len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use crate::gui::animated_image::AnimatedImage;
+use chrono::Utc;
+use dgg::common::cache::JsonCache;
+use dgg::dgg::models::emote::Emote;
+use eframe::egui;
+use eframe::egui::{Color32, Pos2, ScrollArea, Ui};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const CACHE_KEY: &str = "emote_usage";
+const MAX_FREQUENT: usize = 16;
+const EMOTE_SIZE: f32 = 28.0;
+const SECONDS_PER_DAY: f64 = 24.0 * 60.0 * 60.0;
+
+type EmoteEntry = (Rc<Emote>, Rc<AnimatedImage>);
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct EmoteUsage {
+    count: u32,
+    /// Unix timestamp in seconds.
+    last_used: i64,
+}
+
+impl EmoteUsage {
+    /// Higher for emotes used often, decaying by the number of days since the last use.
+    fn score(&self, now: i64) -> f64 {
+        let days = (now - self.last_used).max(0) as f64 / SECONDS_PER_DAY;
+        self.count as f64 / (1.0 + days)
+    }
+}
+
+/// A popup grid of all emotes, grouped by theme, with the ones used most recently and frequently
+/// first.
+#[derive(Default)]
+pub struct EmotePicker {
+    pub is_open: bool,
+    search: String,
+    emotes: Vec<EmoteEntry>,
+    sub_tier: u8,
+    usage: HashMap<String, EmoteUsage>,
+    cache: Option<JsonCache>,
+}
+
+impl EmotePicker {
+    /// Loads the emote usage from a cache at `cache_path`, if given.
+    pub fn new(cache_path: Option<PathBuf>) -> Self {
+        let mut cache = cache_path.map(JsonCache::new);
+        let usage = cache
+            .as_mut()
+            .and_then(|cache| {
+                cache
+                    .get::<HashMap<String, EmoteUsage>>(CACHE_KEY.to_string())
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to load emote usage: {:?}", e);
+                        None
+                    })
+            })
+            .unwrap_or_default();
+
+        Self {
+            usage,
+            cache,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_emotes(
+        &mut self,
+        emotes: &HashMap<String, Rc<Emote>>,
+        images: &HashMap<String, Rc<AnimatedImage>>,
+    ) {
+        self.emotes = emotes
+            .values()
+            .filter_map(|emote| Some((emote.clone(), images.get(&emote.prefix)?.clone())))
+            .collect();
+        self.emotes
+            .sort_by(|(a, _), (b, _)| a.prefix.cmp(&b.prefix));
+    }
+
+    /// Sets the subscription tier of the current user, above which emotes can't be used.
+    pub fn set_sub_tier(&mut self, sub_tier: u8) {
+        self.sub_tier = sub_tier;
+    }
+
+    /// Counts every emote in a sent message as used.
+    pub fn note_sent(&mut self, text: &str) {
+        let now = Utc::now().timestamp();
+        let mut is_changed = false;
+        for word in text.split_whitespace() {
+            if self.emotes.iter().any(|(emote, _)| emote.prefix == word) {
+                let usage = self.usage.entry(word.to_string()).or_default();
+                usage.count += 1;
+                usage.last_used = now;
+                is_changed = true;
+            }
+        }
+
+        if !is_changed {
+            return;
+        }
+
+        if let Some(cache) = self.cache.as_mut() {
+            cache
+                .set(CACHE_KEY.to_string(), self.usage.clone())
+                .map(|_| ())
+                .unwrap_or_else(|e| error!("Failed to save emote usage: {:?}", e));
+        }
+    }
+
+    /// The prefixes of the most used emotes, best first.
+    fn frequent(&self) -> Vec<&str> {
+        let now = Utc::now().timestamp();
+        let mut frequent: Vec<(&str, f64)> = self
+            .usage
+            .iter()
+            .map(|(prefix, usage)| (prefix.as_str(), usage.score(now)))
+            .collect();
+        frequent.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then(a.cmp(b)));
+        frequent
+            .into_iter()
+            .take(MAX_FREQUENT)
+            .map(|(prefix, _)| prefix)
+            .collect()
+    }
+
+    /// Shows the picker with its bottom right corner at `bottom_right` if it is open, and
+    /// returns the prefix of the emote that was clicked.
+    pub fn show(&mut self, ui: &Ui, bottom_right: Pos2) -> Option<String> {
+        if !self.is_open {
+            return None;
+        }
+
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.is_open = false;
+            return None;
+        }
+
+        let search = self.search.to_lowercase();
+        let matches = |emote: &Emote| emote.prefix.to_lowercase().contains(&search);
+
+        let frequent: Vec<&EmoteEntry> = self
+            .frequent()
+            .into_iter()
+            .filter_map(|prefix| self.emotes.iter().find(|(e, _)| e.prefix == prefix))
+            .filter(|(emote, _)| matches(emote))
+            .collect();
+        let mut themes: BTreeMap<u8, Vec<&EmoteEntry>> = BTreeMap::new();
+        for entry in self.emotes.iter().filter(|(emote, _)| matches(emote)) {
+            themes.entry(entry.0.theme).or_default().push(entry);
+        }
+
+        let mut clicked = None;
+        let mut search = self.search.clone();
+        egui::Area::new(ui.id().with("emote_picker"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::RIGHT_BOTTOM)
+            .fixed_pos(bottom_right)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(320.0);
+                    ui.add(egui::TextEdit::singleline(&mut search).hint_text("Search emotes"));
+
+                    ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        if !frequent.is_empty() {
+                            ui.label("Frequently used");
+                            clicked = clicked.take().or(self.show_grid(ui, &frequent));
+                        }
+
+                        for (theme, emotes) in themes.iter() {
+                            match theme {
+                                0 => ui.label("Default"),
+                                theme => ui.label(format!("Theme {}", theme)),
+                            };
+                            clicked = clicked.take().or(self.show_grid(ui, emotes));
+                        }
+                    });
+                });
+            });
+
+        self.search = search;
+        clicked
+    }
+
+    fn show_grid(&self, ui: &mut Ui, emotes: &[&EmoteEntry]) -> Option<String> {
+        let mut clicked = None;
+        ui.horizontal_wrapped(|ui| {
+            for (emote, image) in emotes {
+                let is_usable = emote.minimum_sub_tier <= self.sub_tier;
+                let size = image.size_vec2() * (EMOTE_SIZE / image.size_vec2().y);
+                let tint = if is_usable {
+                    Color32::WHITE
+                } else {
+                    Color32::from_white_alpha(60)
+                };
+                let hover_text = match &emote.creator {
+                    Some(creator) => format!("{}\nby {}", emote.prefix, creator),
+                    None => emote.prefix.clone(),
+                };
+
+                let response = ui
+                    .add_enabled(
+                        is_usable,
+                        egui::ImageButton::new(image.texture_id(ui.ctx()), size)
+                            .frame(false)
+                            .tint(tint),
+                    )
+                    .on_hover_text(&hover_text)
+                    .on_disabled_hover_text(format!(
+                        "{}\nRequires tier {} subscription",
+                        hover_text, emote.minimum_sub_tier
+                    ));
+                if response.clicked() {
+                    clicked = Some(emote.prefix.clone());
+                }
+            }
+        });
+        clicked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_use_outweighs_old_frequent_use() {
+        let now = Utc::now().timestamp();
+        let old = EmoteUsage {
+            count: 10,
+            last_used: now - 30 * SECONDS_PER_DAY as i64,
+        };
+        let recent = EmoteUsage {
+            count: 3,
+            last_used: now,
+        };
+
+        assert!(recent.score(now) > old.score(now));
+    }
+}
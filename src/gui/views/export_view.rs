@@ -0,0 +1,152 @@
+use crate::gui::app_services::StatusSender;
+use crate::gui::views::task_status::TaskStatus;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use dgg::dgg::export::ExportFormat;
+use eframe::egui;
+use eframe::egui::{Color32, Context, Ui};
+use std::path::PathBuf;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// The options chosen in the [ExportView].
+pub struct ExportOptions {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+    pub status: StatusSender,
+}
+
+/// A window for exporting a time range of the chat history to a file.
+pub struct ExportView {
+    pub is_open: bool,
+    format: ExportFormat,
+    /// Times in UTC, or empty for no limit.
+    after: String,
+    before: String,
+    path: String,
+    /// How the last export went, shown below the options.
+    status: TaskStatus,
+}
+
+impl Default for ExportView {
+    fn default() -> Self {
+        let format = ExportFormat::Text;
+        Self {
+            is_open: false,
+            format,
+            after: Utc::now().format("%Y-%m-%d 00:00").to_string(),
+            before: String::new(),
+            path: default_path(format).display().to_string(),
+            status: TaskStatus::default(),
+        }
+    }
+}
+
+impl ExportView {
+    /// Shows the window if it's open. Returns the chosen options when the export is started.
+    pub fn show(&mut self, ctx: &Context) -> Option<ExportOptions> {
+        self.status.update();
+        let mut options = None;
+        let mut is_open = self.is_open;
+        egui::Window::new("Export chat")
+            .open(&mut is_open)
+            .default_width(400.0)
+            .show(ctx, |ui| options = self.show_contents(ui));
+        self.is_open = is_open;
+        options
+    }
+
+    fn show_contents(&mut self, ui: &mut Ui) -> Option<ExportOptions> {
+        let after = parse_time(&self.after);
+        let before = parse_time(&self.before);
+
+        egui::Grid::new("export_options")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Format");
+                let previous_format = self.format;
+                egui::ComboBox::from_id_source("export_format")
+                    .selected_text(self.format.label())
+                    .show_ui(ui, |ui| {
+                        for format in ExportFormat::ALL {
+                            ui.selectable_value(&mut self.format, format, format.label());
+                        }
+                    });
+                if self.format != previous_format {
+                    self.path = PathBuf::from(&self.path)
+                        .with_extension(self.format.extension())
+                        .display()
+                        .to_string();
+                }
+                ui.end_row();
+
+                for (label, text, time) in [
+                    ("From (UTC)", &mut self.after, &after),
+                    ("To (UTC)", &mut self.before, &before),
+                ] {
+                    ui.label(label);
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(text).hint_text(TIME_FORMAT));
+                        if time.is_err() {
+                            ui.colored_label(Color32::RED, "Invalid");
+                        }
+                    });
+                    ui.end_row();
+                }
+
+                ui.label("File");
+                ui.text_edit_singleline(&mut self.path);
+                ui.end_row();
+            });
+        ui.weak("Messages hidden by the ignore list and filters are left out.");
+
+        self.status.show(ui);
+
+        let (Ok(after), Ok(before)) = (after, before) else {
+            return None;
+        };
+        let is_valid = !self.path.trim().is_empty() && !self.status.is_running();
+        if ui
+            .add_enabled(is_valid, egui::Button::new("Export"))
+            .clicked()
+        {
+            let status = self
+                .status
+                .start(format!("Exporting to {}", self.path.trim()));
+            return Some(ExportOptions {
+                path: PathBuf::from(self.path.trim()),
+                format: self.format,
+                after,
+                before,
+                status,
+            });
+        }
+        None
+    }
+}
+
+fn default_path(format: ExportFormat) -> PathBuf {
+    let name = format!(
+        "dgg-chat-{}.{}",
+        Utc::now().format("%Y-%m-%d"),
+        format.extension()
+    );
+    dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join(name)
+}
+
+/// Parses `YYYY-MM-DD HH:MM` or `YYYY-MM-DD` in UTC, or an empty string as no limit.
+fn parse_time(text: &str) -> Result<Option<DateTime<Utc>>, chrono::ParseError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    let naive = NaiveDateTime::parse_from_str(text, TIME_FORMAT).or_else(|_| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN))
+    })?;
+    Ok(Some(Utc.from_utc_datetime(&naive)))
+}
@@ -0,0 +1,97 @@
+use crate::gui::app_services::{Reply, StatusSender};
+use crate::gui::views::task_status::TaskStatus;
+use dgg::dgg::models::event::Event;
+use eframe::egui;
+use eframe::egui::{Context, Ui};
+use std::path::PathBuf;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// The options chosen in the [ImportView].
+pub struct ImportOptions {
+    pub path: PathBuf,
+    pub save_to_history: bool,
+    pub log: Reply<Vec<Event>>,
+    pub status: StatusSender,
+}
+
+/// The messages of a log that finished loading.
+pub struct LoadedLog {
+    /// The file name of the log, without its extension.
+    pub name: String,
+    pub events: Vec<Event>,
+}
+
+/// A window for loading an OverRustle daily log, to read a past day in the chat.
+#[derive(Default)]
+pub struct ImportView {
+    pub is_open: bool,
+    path: String,
+    save_to_history: bool,
+    /// How the last import went, shown below the options.
+    status: TaskStatus,
+    /// The name of the log being loaded, and where its messages arrive.
+    log_rx: Option<(String, UnboundedReceiver<Vec<Event>>)>,
+}
+
+impl ImportView {
+    /// Shows the window if it's open. Returns the chosen options when the import is started.
+    /// The log that was being loaded, once its messages arrive.
+    pub fn take_log(&mut self) -> Option<LoadedLog> {
+        let (_, log_rx) = self.log_rx.as_mut()?;
+        let events = log_rx.try_recv().ok()?;
+        let (name, _) = self.log_rx.take()?;
+        Some(LoadedLog { name, events })
+    }
+
+    pub fn show(&mut self, ctx: &Context) -> Option<ImportOptions> {
+        self.status.update();
+        let mut options = None;
+        let mut is_open = self.is_open;
+        egui::Window::new("Import OverRustle log")
+            .open(&mut is_open)
+            .default_width(400.0)
+            .show(ctx, |ui| options = self.show_contents(ui));
+        self.is_open = is_open;
+        options
+    }
+
+    fn show_contents(&mut self, ui: &mut Ui) -> Option<ImportOptions> {
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.path)
+                    .hint_text("Destinygg chatlog/June 2023/2023-06-25.txt")
+                    .desired_width(f32::INFINITY),
+            );
+        });
+        ui.checkbox(&mut self.save_to_history, "Save to history")
+            .on_hover_text(
+                "Keep the messages in the local history, so they can be searched and exported",
+            );
+        ui.weak("The log is shown in place of the live chat, until you go back to it.");
+
+        self.status.show(ui);
+
+        let path = self.path.trim();
+        if ui
+            .add_enabled(
+                !path.is_empty() && !self.status.is_running(),
+                egui::Button::new("Import"),
+            )
+            .clicked()
+        {
+            let path = PathBuf::from(path);
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let (log, log_rx) = Reply::channel();
+            self.log_rx = Some((name.into_owned(), log_rx));
+            let status = self.status.start(format!("Loading {}", path.display()));
+            return Some(ImportOptions {
+                path,
+                save_to_history: self.save_to_history,
+                log,
+                status,
+            });
+        }
+        None
+    }
+}
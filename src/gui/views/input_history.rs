@@ -0,0 +1,122 @@
+use dgg::common::cache::JsonCache;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+const MAX_ENTRIES: usize = 100;
+const CACHE_KEY: &str = "input_history";
+
+/// Previously sent inputs, browsable like a shell history.
+#[derive(Debug, Default)]
+pub struct InputHistory {
+    /// Oldest first.
+    entries: VecDeque<String>,
+    /// The entry being shown while browsing.
+    position: Option<usize>,
+    /// The unsent input from before browsing started.
+    draft: String,
+    cache: Option<JsonCache>,
+}
+
+impl InputHistory {
+    /// Loads the history from a cache at `cache_path`, if given.
+    pub fn new(cache_path: Option<PathBuf>) -> Self {
+        let mut cache = cache_path.map(JsonCache::new);
+        let entries = cache
+            .as_mut()
+            .and_then(|cache| {
+                cache
+                    .get::<VecDeque<String>>(CACHE_KEY.to_string())
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to load input history: {:?}", e);
+                        None
+                    })
+            })
+            .unwrap_or_default();
+
+        Self {
+            entries,
+            cache,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, entry: String) {
+        self.position = None;
+        self.draft.clear();
+
+        if entry.trim().is_empty() || self.entries.back() == Some(&entry) {
+            return;
+        }
+
+        self.entries.push_back(entry);
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+
+        if let Some(cache) = self.cache.as_mut() {
+            cache
+                .set(CACHE_KEY.to_string(), self.entries.clone())
+                .map(|_| ())
+                .unwrap_or_else(|e| error!("Failed to save input history: {:?}", e));
+        }
+    }
+
+    /// Steps back in the history, remembering `current` as the draft if browsing just started.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(position) => position - 1,
+        };
+
+        self.position = Some(position);
+        self.entries.get(position).map(String::as_str)
+    }
+
+    /// Steps forward in the history, ending with the draft.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            self.entries.get(position).map(String::as_str)
+        } else {
+            self.position = None;
+            Some(self.draft.as_str())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browsing_preserves_the_draft() {
+        let mut history = InputHistory::default();
+        history.push("first".to_string());
+        history.push("second".to_string());
+
+        assert_eq!(history.previous("draft"), Some("second"));
+        assert_eq!(history.previous("second"), Some("first"));
+        assert_eq!(history.previous("first"), None);
+        assert_eq!(history.next(), Some("second"));
+        assert_eq!(history.next(), Some("draft"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn is_bounded_and_skips_repeats() {
+        let mut history = InputHistory::default();
+        for i in 0..MAX_ENTRIES + 10 {
+            history.push(i.to_string());
+            history.push(i.to_string());
+        }
+
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries.front().map(String::as_str), Some("10"));
+    }
+}
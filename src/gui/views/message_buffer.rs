@@ -0,0 +1,172 @@
+use crate::gui::views::chat_message_view::ChatMessageView;
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+/// The chat messages, oldest first, which drops the oldest ones once there are more than a
+/// given number of them or they take up more than a given amount of memory.
+///
+/// Messages are only dropped by [MessageBuffer::evict], so that the caller can hold off while
+/// the user is reading older messages.
+#[derive(Debug, Default)]
+pub struct MessageBuffer {
+    messages: VecDeque<ChatMessageView>,
+    /// The estimated memory used by the messages, in bytes.
+    size: usize,
+    /// The number of messages ever pushed, which isn't reduced when messages are dropped.
+    pushed_count: usize,
+    /// Changed whenever the messages may have changed, so that work derived from them can be
+    /// redone only when needed.
+    generation: usize,
+}
+
+impl Deref for MessageBuffer {
+    type Target = VecDeque<ChatMessageView>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.messages
+    }
+}
+
+impl MessageBuffer {
+    /// The estimated memory used by the messages, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of messages ever pushed, including the ones dropped since.
+    pub fn pushed_count(&self) -> usize {
+        self.pushed_count
+    }
+
+    /// A number that changes whenever the messages may have changed.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Adds a message, numbering it with [ChatMessageView::id].
+    pub fn push(&mut self, mut message: ChatMessageView) {
+        self.generation += 1;
+        self.size += message.estimated_size();
+        message.id = self.pushed_count;
+        self.pushed_count += 1;
+        self.messages.push_back(message);
+    }
+
+    /// The index of the message with the given [ChatMessageView::id], if it's still kept.
+    pub fn position(&self, id: usize) -> Option<usize> {
+        self.messages.binary_search_by_key(&id, |m| m.id).ok()
+    }
+
+    /// Changes the newest message, counting any change in its size.
+    pub fn update_last<R>(&mut self, f: impl FnOnce(&mut ChatMessageView) -> R) -> Option<R> {
+        let message = self.messages.back_mut()?;
+        self.generation += 1;
+        let size = message.estimated_size();
+        let result = f(message);
+        self.size = self.size - size + message.estimated_size();
+        Some(result)
+    }
+
+    /// Changes every message, counting any change in their size.
+    pub fn update_each(&mut self, mut f: impl FnMut(&mut ChatMessageView)) {
+        self.messages.iter_mut().for_each(&mut f);
+        self.recount();
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&ChatMessageView) -> bool) {
+        self.messages.retain(f);
+        self.recount();
+    }
+
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.messages.clear();
+        self.size = 0;
+    }
+
+    /// Drops the oldest messages until there are at most `max_count` of them, using at most
+    /// `max_size` bytes. A limit of 0 means no limit. Returns the number of messages dropped.
+    pub fn evict(&mut self, max_count: usize, max_size: usize) -> usize {
+        let mut count = 0;
+        while self.is_over(max_count, max_size) {
+            let Some(message) = self.messages.pop_front() else {
+                break;
+            };
+            self.size = self.size.saturating_sub(message.estimated_size());
+            count += 1;
+        }
+        if count > 0 {
+            self.generation += 1;
+        }
+        count
+    }
+
+    fn is_over(&self, max_count: usize, max_size: usize) -> bool {
+        (max_count > 0 && self.messages.len() > max_count) || (max_size > 0 && self.size > max_size)
+    }
+
+    fn recount(&mut self) {
+        self.generation += 1;
+        self.size = self.messages.iter().map(|m| m.estimated_size()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    fn message(text: &str) -> ChatMessageView {
+        ChatMessageView::new(
+            "Bob".to_string(),
+            None,
+            false,
+            text.to_string(),
+            Utc::now(),
+            Vec::new(),
+            &HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn evicts_the_oldest_messages_by_count_and_size() {
+        let mut buffer = MessageBuffer::default();
+        for i in 0..5 {
+            buffer.push(message(&i.to_string()));
+        }
+        assert_eq!(buffer.evict(0, 0), 0);
+
+        assert_eq!(buffer.evict(3, 0), 2);
+        assert_eq!(buffer.front().unwrap().message, "2");
+
+        buffer.push(message(&"x".repeat(1000)));
+        let size = buffer.size();
+        assert!(size > 1000);
+        assert_eq!(buffer.evict(0, size - 1), 1);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.back().unwrap().message.len(), 1000);
+
+        buffer.retain(|m| m.message.len() > 1);
+        assert_eq!(buffer.size(), buffer.back().unwrap().estimated_size());
+        assert_eq!(buffer.pushed_count(), 6);
+    }
+
+    #[test]
+    fn keeps_ids_and_sizes_when_messages_change() {
+        let mut buffer = MessageBuffer::default();
+        for i in 0..3 {
+            buffer.push(message(&i.to_string()));
+        }
+        buffer.evict(2, 0);
+        assert_eq!(buffer.position(0), None);
+        assert_eq!(buffer.position(2), Some(1));
+
+        buffer.update_last(|m| m.message = "x".repeat(1000));
+        buffer.update_each(|m| m.message.push('!'));
+        let sizes: usize = buffer.iter().map(|m| m.estimated_size()).sum();
+        assert_eq!(buffer.size(), sizes);
+        assert_eq!(buffer.evict(0, 1), 2);
+        assert_eq!(buffer.size(), 0);
+    }
+}
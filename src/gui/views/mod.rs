@@ -1,3 +1,15 @@
 mod chat_input_view;
 mod chat_message_view;
 pub mod chat_view;
+mod emote_picker;
+mod export_view;
+mod import_view;
+mod input_history;
+mod message_buffer;
+mod pin_banner_view;
+mod search_view;
+mod settings_view;
+mod tab_completion;
+mod task_status;
+mod user_card_view;
+mod whisper_view;
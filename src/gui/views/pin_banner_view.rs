@@ -0,0 +1,104 @@
+use crate::gui::animated_image::AnimatedImage;
+use crate::gui::views::chat_message_view::ChatMessageView;
+use dgg::common::cache::JsonCache;
+use eframe::egui;
+use eframe::egui::Ui;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const CACHE_KEY: &str = "dismissed_pins";
+
+/// The pinned message shown above the chat, until it is dismissed.
+#[derive(Default)]
+pub struct PinBannerView {
+    /// The uuid and message of the current pin.
+    pin: Option<(String, ChatMessageView)>,
+    /// Uuids of the pins that have been dismissed, which stay hidden after a restart.
+    dismissed: HashSet<String>,
+    cache: Option<JsonCache>,
+}
+
+impl PinBannerView {
+    /// Loads the dismissed pins from a cache at `cache_path`, if given.
+    pub fn new(cache_path: Option<PathBuf>) -> Self {
+        let mut cache = cache_path.map(JsonCache::new);
+        let dismissed = cache
+            .as_mut()
+            .and_then(|cache| {
+                cache
+                    .get::<HashSet<String>>(CACHE_KEY.to_string())
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to load dismissed pins: {:?}", e);
+                        None
+                    })
+            })
+            .unwrap_or_default();
+
+        Self {
+            pin: None,
+            dismissed,
+            cache,
+        }
+    }
+
+    /// Replaces the current pin, or removes it if `message` is `None`.
+    pub fn set_pin(&mut self, uuid: String, message: Option<ChatMessageView>) {
+        self.pin = message.map(|message| (uuid, message));
+    }
+
+    /// Parses the pinned message again with a new set of emotes, since the pin usually arrives
+    /// before they have loaded.
+    pub fn set_emotes(&mut self, emote_images: &HashMap<String, Rc<AnimatedImage>>) {
+        if let Some((_, message)) = self.pin.as_mut() {
+            message.set_emotes(emote_images);
+        }
+    }
+
+    fn dismiss(&mut self, uuid: String) {
+        self.dismissed.insert(uuid);
+
+        if let Some(cache) = self.cache.as_mut() {
+            cache
+                .set(CACHE_KEY.to_string(), self.dismissed.clone())
+                .map(|_| ())
+                .unwrap_or_else(|e| error!("Failed to save dismissed pins: {:?}", e));
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        let Some((uuid, message)) = &self.pin else {
+            return;
+        };
+        if self.dismissed.contains(uuid) {
+            return;
+        }
+
+        let mut is_dismissed = false;
+        egui::Frame::group(ui.style())
+            .fill(ui.visuals().faint_bg_color)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.strong("📌 Pinned message").on_hover_text(
+                        message
+                            .sent_at
+                            .format("Pinned on %Y-%m-%d at %H:%M:%S UTC")
+                            .to_string(),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        is_dismissed = ui
+                            .small_button("✕")
+                            .on_hover_text("Dismiss this pin")
+                            .clicked();
+                    });
+                });
+                message.show_grouped(ui, false);
+            });
+
+        if is_dismissed {
+            let uuid = uuid.clone();
+            self.dismiss(uuid);
+        }
+    }
+}
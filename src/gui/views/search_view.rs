@@ -0,0 +1,195 @@
+use chrono::Utc;
+use dgg::dgg::search::SearchQuery;
+use eframe::egui;
+use eframe::egui::{Key, Modifiers, Ui};
+
+/// A search bar over the chat messages, opened with Ctrl+F, which steps through the matching
+/// messages from the newest to the oldest.
+#[derive(Default)]
+pub struct SearchView {
+    pub is_open: bool,
+    text: String,
+    /// The query parsed from `text` when it last changed.
+    query: SearchQuery,
+    /// The [MessageBuffer] generation that `matches` were found in, or `None` if the matches
+    /// are outdated.
+    ///
+    /// [MessageBuffer]: crate::gui::views::message_buffer::MessageBuffer
+    searched_generation: Option<usize>,
+    /// Ids of the matching messages, oldest first, which stay the same as older messages are
+    /// dropped.
+    matches: Vec<usize>,
+    /// The index into `matches` of the selected match.
+    current: Option<usize>,
+    /// A message to scroll to once.
+    scroll_target: Option<usize>,
+    /// Set when the query changed, to select the newest match once it is known.
+    select_newest: bool,
+    request_focus: bool,
+}
+
+impl SearchView {
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.request_focus = true;
+        self.search_again();
+    }
+
+    fn close(&mut self) {
+        self.is_open = false;
+        self.matches.clear();
+        self.current = None;
+        self.searched_generation = None;
+    }
+
+    /// Searches again from the newest match, e.g. when the text or the searched messages
+    /// changed.
+    pub fn search_again(&mut self) {
+        self.query = SearchQuery::parse(&self.text, Utc::now());
+        self.searched_generation = None;
+        self.select_newest = true;
+    }
+
+    /// The query to match the messages of `generation` with, if the search bar is open and
+    /// the query or the messages changed since the matches were last set.
+    pub fn outdated_query(&mut self, generation: usize) -> Option<SearchQuery> {
+        if !self.is_open || self.searched_generation == Some(generation) {
+            return None;
+        }
+        self.searched_generation = Some(generation);
+        Some(self.query.clone())
+    }
+
+    /// Updates the matching messages, keeping the same one selected if it still matches.
+    pub fn set_matches(&mut self, matches: Vec<usize>) {
+        let selected = self.current.and_then(|i| self.matches.get(i)).copied();
+        self.current = selected.and_then(|s| matches.iter().position(|m| *m == s));
+        self.matches = matches;
+
+        if std::mem::take(&mut self.select_newest) {
+            self.current = None;
+            self.step(true);
+        }
+    }
+
+    /// `Some(true)` for the selected match, and `Some(false)` for any other, by message id.
+    pub fn match_state(&self, id: usize) -> Option<bool> {
+        let position = self.matches.binary_search(&id).ok()?;
+        Some(self.current == Some(position))
+    }
+
+    /// The id of the message to scroll to, if the selection just changed.
+    pub fn take_scroll_target(&mut self) -> Option<usize> {
+        self.scroll_target.take()
+    }
+
+    /// Moves the selection to an older match if `older`, or else to a newer one.
+    fn step(&mut self, older: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+
+        let last = self.matches.len() - 1;
+        let current = match (self.current, older) {
+            (None, _) => last,
+            (Some(0), true) => last,
+            (Some(i), true) => i - 1,
+            (Some(i), false) if i == last => 0,
+            (Some(i), false) => i + 1,
+        };
+        self.current = Some(current);
+        self.scroll_target = Some(self.matches[current]);
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if !self.is_open {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.text)
+                    .hint_text("Search, e.g. from:nick flair:moderator has:link after:12:00")
+                    .desired_width(ui.available_width() - 160.0),
+            );
+            if std::mem::take(&mut self.request_focus) {
+                response.request_focus();
+            }
+            if response.changed() {
+                self.search_again();
+            }
+            if response.lost_focus() {
+                if ui.input(|i| i.key_pressed(Key::Escape)) {
+                    self.close();
+                    return;
+                }
+                if ui.input(|i| i.key_pressed(Key::Enter)) {
+                    let is_newer = ui.input(|i| i.modifiers.matches(Modifiers::SHIFT));
+                    self.step(!is_newer);
+                    response.request_focus();
+                }
+            }
+
+            match self.current {
+                Some(current) => ui.label(format!(
+                    "{} of {}",
+                    self.matches.len() - current,
+                    self.matches.len()
+                )),
+                None => ui.weak(format!("{} matches", self.matches.len())),
+            };
+            if ui
+                .small_button("⏶")
+                .on_hover_text("Older (Enter)")
+                .clicked()
+            {
+                self.step(true);
+            }
+            if ui
+                .small_button("⏷")
+                .on_hover_text("Newer (Shift+Enter)")
+                .clicked()
+            {
+                self.step(false);
+            }
+            if ui.small_button("✕").on_hover_text("Close (Esc)").clicked() {
+                self.close();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_from_the_newest_match_and_wraps() {
+        let mut view = SearchView::default();
+        view.set_matches(vec![2, 5, 9]);
+
+        view.step(true);
+        assert_eq!(view.take_scroll_target(), Some(9));
+        view.step(false);
+        assert_eq!(view.take_scroll_target(), Some(2));
+
+        view.set_matches(vec![1, 2, 5]);
+        assert_eq!(view.match_state(2), Some(true));
+        assert_eq!(view.match_state(5), Some(false));
+        assert_eq!(view.match_state(3), None);
+    }
+
+    #[test]
+    fn searches_again_only_after_a_change() {
+        let mut view = SearchView::default();
+        assert!(view.outdated_query(1).is_none());
+
+        view.open();
+        assert!(view.outdated_query(1).is_some());
+        assert!(view.outdated_query(1).is_none());
+        assert!(view.outdated_query(2).is_some());
+
+        view.search_again();
+        assert!(view.outdated_query(2).is_some());
+    }
+}
@@ -0,0 +1,258 @@
+use dgg::dgg::filter::{FilterAction, FilterRule, FilterSettings};
+use dgg::settings::{CensorStyle, ChatAppSettings, DisplaySettings, SystemMessageSettings};
+use eframe::egui;
+use eframe::egui::{Color32, Context, Ui};
+use std::time::{Duration, Instant};
+
+/// How long to wait after the last change before saving the settings, so that typing into a
+/// field doesn't write the file on every keystroke.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// A window for editing the [ChatAppSettings] that aren't covered by menus.
+#[derive(Default)]
+pub struct SettingsView {
+    pub is_open: bool,
+    /// When the settings were last changed, if that wasn't saved yet.
+    unsaved_since: Option<Instant>,
+}
+
+impl SettingsView {
+    /// Whether the changed settings should be saved now, which is once they haven't changed for
+    /// a moment or the window was closed.
+    pub fn should_save(&mut self, ctx: &Context) -> bool {
+        let Some(changed_at) = self.unsaved_since else {
+            return false;
+        };
+
+        let elapsed = changed_at.elapsed();
+        if self.is_open && elapsed < SAVE_DELAY {
+            ctx.request_repaint_after(SAVE_DELAY - elapsed);
+            return false;
+        }
+        self.unsaved_since = None;
+        true
+    }
+
+    /// Shows the window if it's open. Returns `true` if the settings changed.
+    pub fn show(&mut self, ctx: &Context, settings: &mut ChatAppSettings) -> bool {
+        let mut changed = false;
+
+        egui::Window::new("Settings")
+            .open(&mut self.is_open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Display");
+                    changed |= show_display_settings(ui, &mut settings.display);
+
+                    ui.separator();
+                    ui.heading("System messages");
+                    changed |= show_system_message_settings(ui, &mut settings.system_messages);
+
+                    ui.separator();
+                    ui.heading("Filters");
+                    changed |= show_filter_settings(ui, &mut settings.filters);
+                });
+            });
+
+        if changed {
+            self.unsaved_since = Some(Instant::now());
+        }
+        changed
+    }
+}
+
+fn show_display_settings(ui: &mut Ui, display: &mut DisplaySettings) -> bool {
+    let mut changed = ui
+        .checkbox(
+            &mut display.pause_animations_when_unfocused,
+            "Pause animated emotes when the window is unfocused",
+        )
+        .changed();
+    changed |= ui
+        .add(egui::Slider::new(&mut display.emote_scale, 0.5..=3.0).text("Emote scale"))
+        .on_hover_text(
+            "Images are downloaded at a resolution for the scale on startup. Restart for \
+             sharper emotes after changing it.",
+        )
+        .changed();
+    changed |= ui
+        .checkbox(
+            &mut display.group_messages,
+            "Group consecutive messages from the same user",
+        )
+        .changed();
+    ui.horizontal(|ui| {
+        ui.label("Messages of muted and banned users");
+        egui::ComboBox::from_id_source("censor_style")
+            .selected_text(display.censor_style.label())
+            .show_ui(ui, |ui| {
+                for style in CensorStyle::ALL {
+                    changed |= ui
+                        .selectable_value(&mut display.censor_style, style, style.label())
+                        .changed();
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        ui.label("Keep at most");
+        changed |= ui
+            .add(egui::DragValue::new(&mut display.max_messages).speed(100.0))
+            .changed();
+        ui.label("messages, using at most");
+        changed |= ui
+            .add(egui::DragValue::new(&mut display.max_message_memory_mb).suffix(" MB"))
+            .changed();
+    })
+    .response
+    .on_hover_text("Older messages are dropped while scrolled to the bottom. 0 means no limit.");
+    changed
+}
+
+fn show_system_message_settings(ui: &mut Ui, system_messages: &mut SystemMessageSettings) -> bool {
+    let mut changed = false;
+    for (value, label) in [
+        (&mut system_messages.broadcasts, "Broadcasts"),
+        (&mut system_messages.mutes, "Mutes"),
+        (&mut system_messages.unmutes, "Unmutes"),
+        (&mut system_messages.bans, "Bans"),
+        (&mut system_messages.unbans, "Unbans"),
+        (
+            &mut system_messages.sub_only,
+            "Subscriber-only mode changes",
+        ),
+    ] {
+        changed |= ui.checkbox(value, label).changed();
+    }
+    changed
+}
+
+fn show_filter_settings(ui: &mut Ui, filters: &mut FilterSettings) -> bool {
+    let mut changed = false;
+    let mut removed = None;
+
+    ui.weak("Rules are checked in order, and the first one that matches applies.");
+    for (i, rule) in filters.rules.iter_mut().enumerate() {
+        let title = if rule.name.is_empty() {
+            format!("Rule {}", i + 1)
+        } else {
+            rule.name.clone()
+        };
+
+        egui::CollapsingHeader::new(title)
+            .id_source(("filter_rule", i))
+            .show(ui, |ui| {
+                changed |= show_filter_rule(ui, i, rule);
+                if ui.button("Remove").clicked() {
+                    removed = Some(i);
+                }
+            });
+    }
+
+    if let Some(i) = removed {
+        filters.rules.remove(i);
+        changed = true;
+    }
+
+    if ui.button("Add rule").clicked() {
+        filters.rules.push(FilterRule::default());
+        changed = true;
+    }
+
+    changed
+}
+
+fn show_filter_rule(ui: &mut Ui, index: usize, rule: &mut FilterRule) -> bool {
+    let mut changed = false;
+
+    egui::Grid::new(("filter_rule_grid", index))
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Name");
+            changed |= ui.text_edit_singleline(&mut rule.name).changed();
+            ui.end_row();
+
+            ui.label("Enabled");
+            changed |= ui.checkbox(&mut rule.enabled, "").changed();
+            ui.end_row();
+
+            ui.label("Action");
+            egui::ComboBox::from_id_source(("filter_rule_action", index))
+                .selected_text(rule.action.label())
+                .show_ui(ui, |ui| {
+                    for action in FilterAction::ALL {
+                        changed |= ui
+                            .selectable_value(&mut rule.action, action, action.label())
+                            .changed();
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Text (regex)");
+            ui.horizontal(|ui| {
+                changed |= optional_text_edit(ui, &mut rule.text);
+                if !rule.has_valid_pattern() {
+                    ui.colored_label(Color32::RED, "Invalid");
+                }
+            });
+            ui.end_row();
+
+            ui.label("Nick");
+            changed |= optional_text_edit(ui, &mut rule.nick);
+            ui.end_row();
+
+            ui.label("Flair");
+            changed |= optional_text_edit(ui, &mut rule.flair);
+            ui.end_row();
+
+            ui.label("Has link");
+            changed |= optional_bool_combo_box(ui, ("filter_rule_link", index), &mut rule.has_link);
+            ui.end_row();
+
+            ui.label("Emote only");
+            changed |= optional_bool_combo_box(
+                ui,
+                ("filter_rule_emote_only", index),
+                &mut rule.emote_only,
+            );
+            ui.end_row();
+        });
+
+    if changed {
+        rule.compile().ok();
+    }
+
+    changed
+}
+
+/// A text edit where an empty string means `None`.
+fn optional_text_edit(ui: &mut Ui, value: &mut Option<String>) -> bool {
+    let mut text = value.clone().unwrap_or_default();
+    let changed = ui.text_edit_singleline(&mut text).changed();
+    if changed {
+        *value = Some(text).filter(|t| !t.is_empty());
+    }
+    changed
+}
+
+fn optional_bool_combo_box(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    value: &mut Option<bool>,
+) -> bool {
+    let label = |value: Option<bool>| match value {
+        None => "Any",
+        Some(true) => "Yes",
+        Some(false) => "No",
+    };
+
+    let mut changed = false;
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(label(*value))
+        .show_ui(ui, |ui| {
+            for option in [None, Some(true), Some(false)] {
+                changed |= ui.selectable_value(value, option, label(option)).changed();
+            }
+        });
+    changed
+}
@@ -0,0 +1,255 @@
+use crate::gui::animated_image::AnimatedImage;
+use eframe::egui;
+use eframe::egui::{Pos2, Ui};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::rc::Rc;
+
+const MAX_RECENT_NICKS: usize = 100;
+const MAX_CANDIDATES: usize = 50;
+const MAX_VISIBLE_SUGGESTIONS: usize = 8;
+
+#[derive(Clone)]
+enum Candidate {
+    Nick(String),
+    Emote(String, Rc<AnimatedImage>),
+}
+
+impl Candidate {
+    fn text(&self) -> &str {
+        match self {
+            Candidate::Nick(nick) => nick,
+            Candidate::Emote(prefix, _) => prefix,
+        }
+    }
+}
+
+/// An ongoing completion, which is cycled through as long as the text isn't edited.
+struct Completion {
+    /// The char index where the completed word starts.
+    word_start: usize,
+    candidates: Vec<Candidate>,
+    index: usize,
+    /// The text and cursor as left by the last completion.
+    expected_text: String,
+    expected_cursor: usize,
+}
+
+/// Completes nicks and emote prefixes in the chat input.
+///
+/// Nicks of recently active users are suggested first, then emotes, then everyone else who is
+/// connected.
+#[derive(Default)]
+pub struct TabCompleter {
+    recent_nicks: VecDeque<String>,
+    connected_nicks: BTreeSet<String>,
+    emotes: BTreeMap<String, Rc<AnimatedImage>>,
+    completion: Option<Completion>,
+}
+
+impl TabCompleter {
+    pub fn set_emotes(&mut self, emotes: &HashMap<String, Rc<AnimatedImage>>) {
+        self.emotes = emotes
+            .iter()
+            .map(|(prefix, image)| (prefix.clone(), image.clone()))
+            .collect();
+    }
+
+    /// Moves `nick` to the front of the recently active users.
+    pub fn note_active(&mut self, nick: &str) {
+        self.recent_nicks.retain(|n| n != nick);
+        self.recent_nicks.push_front(nick.to_string());
+        self.recent_nicks.truncate(MAX_RECENT_NICKS);
+    }
+
+    pub fn set_connected(&mut self, nicks: impl IntoIterator<Item = String>) {
+        self.connected_nicks = nicks.into_iter().collect();
+    }
+
+    pub fn add_connected(&mut self, nick: &str) {
+        self.connected_nicks.insert(nick.to_string());
+    }
+
+    pub fn remove_connected(&mut self, nick: &str) {
+        self.connected_nicks.remove(nick);
+    }
+
+    /// Drops the ongoing completion if the text or cursor changed since it was last applied.
+    pub fn update(&mut self, text: &str, cursor: Option<usize>) {
+        if let Some(completion) = &self.completion {
+            if completion.expected_text != text || Some(completion.expected_cursor) != cursor {
+                self.completion = None;
+            }
+        }
+    }
+
+    /// Completes the word before `cursor` (a char index), or cycles through the candidates if a
+    /// completion is ongoing. Returns the new cursor position if the text was changed.
+    pub fn complete(&mut self, text: &mut String, cursor: usize, backwards: bool) -> Option<usize> {
+        self.update(text, Some(cursor));
+
+        if self.completion.is_none() {
+            let word_start = word_start(text, cursor);
+            let word: String = text
+                .chars()
+                .skip(word_start)
+                .take(cursor - word_start)
+                .collect();
+            if word.is_empty() {
+                return None;
+            }
+
+            let candidates = self.candidates(&word);
+            if candidates.is_empty() {
+                return None;
+            }
+
+            self.completion = Some(Completion {
+                word_start,
+                index: if backwards { candidates.len() - 1 } else { 0 },
+                candidates,
+                expected_text: String::new(),
+                expected_cursor: cursor,
+            });
+        } else if let Some(completion) = self.completion.as_mut() {
+            let len = completion.candidates.len();
+            completion.index = if backwards {
+                (completion.index + len - 1) % len
+            } else {
+                (completion.index + 1) % len
+            };
+        }
+
+        let completion = self.completion.as_mut()?;
+        let replacement = completion.candidates[completion.index].text();
+        let start = char_to_byte_index(text, completion.word_start);
+        let end = char_to_byte_index(text, completion.expected_cursor);
+        text.replace_range(start..end, replacement);
+
+        let new_cursor = completion.word_start + replacement.chars().count();
+        completion.expected_text = text.clone();
+        completion.expected_cursor = new_cursor;
+        Some(new_cursor)
+    }
+
+    fn candidates(&self, word: &str) -> Vec<Candidate> {
+        let word = word.to_lowercase();
+        let matches = |s: &str| s.to_lowercase().starts_with(&word);
+
+        let recent = self
+            .recent_nicks
+            .iter()
+            .filter(|nick| matches(nick))
+            .map(|nick| Candidate::Nick(nick.clone()));
+        let emotes = self
+            .emotes
+            .iter()
+            .filter(|(prefix, _)| matches(prefix))
+            .map(|(prefix, image)| Candidate::Emote(prefix.clone(), image.clone()));
+        let connected = self
+            .connected_nicks
+            .iter()
+            .filter(|nick| matches(nick) && !self.recent_nicks.contains(nick))
+            .map(|nick| Candidate::Nick(nick.clone()));
+
+        recent
+            .chain(emotes)
+            .chain(connected)
+            .take(MAX_CANDIDATES)
+            .collect()
+    }
+
+    /// Shows the candidates of the ongoing completion in a popup whose bottom left corner is at
+    /// `bottom_left`.
+    pub fn show_suggestions(&self, ui: &Ui, bottom_left: Pos2) {
+        let Some(completion) = &self.completion else {
+            return;
+        };
+
+        let first = completion
+            .index
+            .saturating_sub(MAX_VISIBLE_SUGGESTIONS / 2)
+            .min(
+                completion
+                    .candidates
+                    .len()
+                    .saturating_sub(MAX_VISIBLE_SUGGESTIONS),
+            );
+
+        egui::Area::new(ui.id().with("tab_completion_suggestions"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .fixed_pos(bottom_left)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (i, candidate) in completion
+                        .candidates
+                        .iter()
+                        .enumerate()
+                        .skip(first)
+                        .take(MAX_VISIBLE_SUGGESTIONS)
+                    {
+                        ui.horizontal(|ui| {
+                            if let Candidate::Emote(_, image) = candidate {
+                                let size = image.size_vec2() * (16.0 / image.size_vec2().y);
+                                ui.image(image.texture_id(ui.ctx()), size);
+                            }
+                            ui.selectable_label(i == completion.index, candidate.text());
+                        });
+                    }
+                });
+            });
+    }
+}
+
+/// The char index of the start of the word ending at `cursor`.
+fn word_start(text: &str, cursor: usize) -> usize {
+    let before_cursor: Vec<char> = text.chars().take(cursor).collect();
+    let word_len = before_cursor
+        .iter()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count();
+    before_cursor.len() - word_len
+}
+
+fn char_to_byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_recent_nicks_before_connected_nicks() {
+        let mut completer = TabCompleter::default();
+        completer.set_connected(["destiny".to_string(), "Dan".to_string()]);
+        completer.note_active("dgg_bot");
+
+        let mut text = "hi d".to_string();
+        let cursor = completer.complete(&mut text, 4, false);
+        assert_eq!((text.as_str(), cursor), ("hi dgg_bot", Some(10)));
+
+        let cursor = completer.complete(&mut text, 10, false);
+        assert_eq!((text.as_str(), cursor), ("hi Dan", Some(6)));
+
+        let cursor = completer.complete(&mut text, 6, true);
+        assert_eq!((text.as_str(), cursor), ("hi dgg_bot", Some(10)));
+    }
+
+    #[test]
+    fn editing_the_text_starts_a_new_completion() {
+        let mut completer = TabCompleter::default();
+        completer.set_connected(["destiny".to_string(), "Dan".to_string()]);
+
+        let mut text = "d".to_string();
+        completer.complete(&mut text, 1, false);
+        assert_eq!(text, "Dan");
+
+        text.push_str(" de");
+        completer.complete(&mut text, 6, false);
+        assert_eq!(text, "Dan destiny");
+    }
+}
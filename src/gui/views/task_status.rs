@@ -0,0 +1,73 @@
+use crate::gui::app_services::StatusSender;
+use eframe::egui::{Color32, Ui};
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// The progress of a long-running command, such as an export, shown in the window that
+/// started it.
+#[derive(Default)]
+pub struct TaskStatus {
+    /// The latest message, or the error the command failed with.
+    message: Option<Result<String, String>>,
+    /// Set while the command is running.
+    status_rx: Option<UnboundedReceiver<Result<String, String>>>,
+}
+
+impl TaskStatus {
+    /// Shows `message` until the command reports back through the returned sender.
+    pub fn start(&mut self, message: String) -> StatusSender {
+        let (status, status_rx) = StatusSender::channel();
+        self.message = Some(Ok(message));
+        self.status_rx = Some(status_rx);
+        status
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status_rx.is_some()
+    }
+
+    /// Checks whether the command has reported back.
+    pub fn update(&mut self) {
+        let Some(status_rx) = self.status_rx.as_mut() else {
+            return;
+        };
+        match status_rx.try_recv() {
+            Ok(result) => self.message = Some(result),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.message = Some(Err("The command was dropped".to_string()))
+            }
+        }
+        self.status_rx = None;
+    }
+
+    pub fn show(&self, ui: &mut Ui) {
+        match &self.message {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(error)) => {
+                ui.colored_label(Color32::RED, error);
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finishes_when_the_command_reports_back() {
+        let mut status = TaskStatus::default();
+        let sender = status.start("Exporting".to_string());
+        status.update();
+        assert!(status.is_running());
+
+        sender.report(Err(anyhow::anyhow!("Disk full")));
+        status.update();
+        assert!(!status.is_running());
+        assert_eq!(status.message, Some(Err("Disk full".to_string())));
+    }
+}
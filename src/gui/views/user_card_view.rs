@@ -0,0 +1,208 @@
+use crate::gui::animated_image::AnimatedImage;
+use crate::gui::app_services::Command;
+use crate::gui::commands::parse_duration;
+use crate::gui::ui_action::UiAction;
+use crate::gui::views::chat_message_view::show_flair_tooltip;
+use chrono::{DateTime, Utc};
+use dgg::dgg::models::flair::Flair;
+use dgg::dgg::models::user::User;
+use eframe::egui;
+use eframe::egui::{Color32, Context, Id, Ui};
+use std::rc::Rc;
+
+/// What the [UserCardView] shows about a user, gathered by the chat view every frame.
+pub struct UserCardInfo<'a> {
+    /// `None` if we haven't seen the user's details yet.
+    pub user: Option<&'a User>,
+    pub flairs: Vec<(Rc<Flair>, Option<Rc<AnimatedImage>>)>,
+    pub is_connected: bool,
+    /// Timestamps and texts, oldest first.
+    pub recent_messages: Vec<(&'a str, &'a str)>,
+    /// Whether we are a moderator, and so can mute or ban the user.
+    pub can_moderate: bool,
+}
+
+/// A window with details about a user and actions on them, opened by clicking their nick.
+pub struct UserCardView {
+    pub nick: String,
+    duration: String,
+    ban_reason: String,
+}
+
+impl UserCardView {
+    const DEFAULT_DURATION: &'static str = "10m";
+
+    pub fn new(nick: String) -> Self {
+        Self {
+            nick,
+            duration: Self::DEFAULT_DURATION.to_string(),
+            ban_reason: String::new(),
+        }
+    }
+
+    /// Shows the window. Returns `false` once it has been closed.
+    pub fn show(&mut self, ctx: &Context, info: &UserCardInfo<'_>) -> bool {
+        let mut is_open = true;
+        egui::Window::new(&self.nick)
+            .id(Id::new("user_card"))
+            .open(&mut is_open)
+            .collapsible(false)
+            .default_width(300.0)
+            .show(ctx, |ui| self.show_contents(ui, info));
+        is_open
+    }
+
+    fn show_contents(&mut self, ui: &mut Ui, info: &UserCardInfo<'_>) {
+        if !info.flairs.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                for (flair, image) in &info.flairs {
+                    let response = match image {
+                        Some(image) => {
+                            ui.image(image.texture_id(ui.ctx()), image.display_size(ui, 1.0))
+                        }
+                        None => ui.label(&flair.label),
+                    };
+                    response.on_hover_ui(|ui| show_flair_tooltip(ui, flair));
+                }
+            });
+        }
+
+        if let Some(user) = info.user {
+            ui.label(format!(
+                "Account age: {} (since {})",
+                format_age(user.created_date, Utc::now()),
+                user.created_date.format("%Y-%m-%d")
+            ));
+        }
+        if info.is_connected {
+            ui.colored_label(Color32::GREEN, "Connected");
+        } else {
+            ui.weak("Not connected");
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Mention").clicked() {
+                UiAction::Mention(self.nick.clone()).push(ui.ctx());
+            }
+            if ui.button("Whisper").clicked() {
+                UiAction::StartWhisper(self.nick.clone()).push(ui.ctx());
+            }
+            if ui.button("Ignore").clicked() {
+                UiAction::Command(Command::Ignore(self.nick.clone())).push(ui.ctx());
+            }
+        });
+
+        if info.can_moderate {
+            ui.separator();
+            self.show_mod_actions(ui);
+        }
+
+        ui.separator();
+        ui.strong("Recent messages");
+        if info.recent_messages.is_empty() {
+            ui.weak("No messages");
+        }
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for (timestamp, message) in &info.recent_messages {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.weak(*timestamp);
+                        ui.label(*message);
+                    });
+                }
+            });
+    }
+
+    fn show_mod_actions(&mut self, ui: &mut Ui) {
+        let duration = parse_duration(&self.duration);
+
+        egui::Grid::new("user_card_mod_actions")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Duration");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.duration).desired_width(60.0));
+                    if duration.is_none() {
+                        ui.colored_label(Color32::RED, "Invalid");
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Ban reason");
+                ui.text_edit_singleline(&mut self.ban_reason);
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            let ctx = ui.ctx().clone();
+            let push = |command: Command| UiAction::Command(command).push(&ctx);
+
+            if ui
+                .add_enabled(duration.is_some(), egui::Button::new("Mute"))
+                .clicked()
+            {
+                push(Command::Mute {
+                    nick: self.nick.clone(),
+                    duration,
+                });
+            }
+            if ui.button("Unmute").clicked() {
+                push(Command::Unmute(self.nick.clone()));
+            }
+
+            let can_ban = duration.is_some() && !self.ban_reason.trim().is_empty();
+            if ui
+                .add_enabled(can_ban, egui::Button::new("Ban"))
+                .on_disabled_hover_text("Needs a duration and a reason")
+                .clicked()
+            {
+                push(Command::Ban {
+                    nick: self.nick.clone(),
+                    reason: self.ban_reason.trim().to_string(),
+                    duration,
+                    ban_ip: false,
+                });
+            }
+            if ui.button("Unban").clicked() {
+                push(Command::Unban(self.nick.clone()));
+            }
+        });
+    }
+}
+
+/// Formats the time since `since` in the largest whole unit, e.g. `3 years`.
+fn format_age(since: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let days = (now - since).num_days().max(0);
+    let (count, unit) = match days {
+        0..=29 => (days, "day"),
+        30..=364 => (days / 30, "month"),
+        _ => (days / 365, "year"),
+    };
+
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn formats_age_in_largest_unit() {
+        let now = Utc::now();
+
+        assert_eq!(format_age(now, now), "0 days");
+        assert_eq!(format_age(now - Duration::days(1), now), "1 day");
+        assert_eq!(format_age(now - Duration::days(65), now), "2 months");
+        assert_eq!(
+            format_age(now - Duration::days(3 * 365 + 10), now),
+            "3 years"
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use crate::gui::app_services::Command;
+use crate::gui::commands::{message_length, split_command};
+use chrono::{DateTime, Utc};
+use dgg::common::cache::JsonCache;
+use dgg::dgg::chat::chat_client::MAX_MESSAGE_LENGTH;
+use eframe::egui;
+use eframe::egui::{Color32, Context, Key, Ui};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::sync::mpsc::Sender;
+
+const CACHE_KEY: &str = "whispers";
+/// The most messages kept per conversation.
+const MAX_MESSAGES: usize = 500;
+const NOTIFICATION_SECONDS: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct WhisperMessage {
+    /// Whether we sent the message, rather than the conversation partner.
+    is_outgoing: bool,
+    text: String,
+    sent_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Conversation {
+    nick: String,
+    messages: Vec<WhisperMessage>,
+    unread_count: usize,
+    #[serde(skip)]
+    input: String,
+}
+
+/// A popup for a whisper that arrived while its conversation wasn't being looked at.
+struct Notification {
+    nick: String,
+    text: String,
+    /// Set the first time the notification is shown.
+    expires_at: Option<f64>,
+}
+
+/// A window with a tab for each whisper conversation, most recent first.
+#[derive(Default)]
+pub struct WhisperView {
+    pub is_open: bool,
+    conversations: Vec<Conversation>,
+    /// The lowercase nick of the open tab.
+    selected: Option<String>,
+    notification: Option<Notification>,
+    cache: Option<JsonCache>,
+    command_tx: Option<Sender<Command>>,
+}
+
+impl WhisperView {
+    /// Loads the conversations from a cache at `cache_path`, if given.
+    pub fn new(command_tx: Sender<Command>, cache_path: Option<PathBuf>) -> Self {
+        let mut cache = cache_path.map(JsonCache::new);
+        let conversations = cache
+            .as_mut()
+            .and_then(|cache| {
+                cache
+                    .get::<Vec<Conversation>>(CACHE_KEY.to_string())
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to load whispers: {:?}", e);
+                        None
+                    })
+            })
+            .unwrap_or_default();
+
+        Self {
+            conversations,
+            cache,
+            command_tx: Some(command_tx),
+            ..Default::default()
+        }
+    }
+
+    /// The number of whispers that haven't been read, across all conversations.
+    pub fn unread_count(&self) -> usize {
+        self.conversations.iter().map(|c| c.unread_count).sum()
+    }
+
+    /// Opens the window on the conversation with `nick`, starting it if needed.
+    pub fn open(&mut self, nick: &str) {
+        self.conversation_mut(nick);
+        self.select(nick);
+        self.is_open = true;
+    }
+
+    pub fn add_incoming(&mut self, nick: &str, text: String, sent_at: DateTime<Utc>) {
+        let is_viewed = self.is_open && self.is_selected(nick);
+        let conversation = self.conversation_mut(nick);
+        if !is_viewed {
+            conversation.unread_count += 1;
+        }
+        conversation.messages.push(WhisperMessage {
+            is_outgoing: false,
+            text: text.clone(),
+            sent_at,
+        });
+        Self::truncate(conversation);
+
+        if !is_viewed {
+            self.notification = Some(Notification {
+                nick: nick.to_string(),
+                text,
+                expires_at: None,
+            });
+        }
+        self.save();
+    }
+
+    pub fn add_outgoing(&mut self, nick: &str, text: String) {
+        let conversation = self.conversation_mut(nick);
+        conversation.messages.push(WhisperMessage {
+            is_outgoing: true,
+            text,
+            sent_at: Utc::now(),
+        });
+        Self::truncate(conversation);
+        self.save();
+    }
+
+    fn truncate(conversation: &mut Conversation) {
+        let excess = conversation.messages.len().saturating_sub(MAX_MESSAGES);
+        conversation.messages.drain(..excess);
+    }
+
+    /// The conversation with `nick`, moved to the front as the most recent one.
+    fn conversation_mut(&mut self, nick: &str) -> &mut Conversation {
+        let index = self
+            .conversations
+            .iter()
+            .position(|c| c.nick.eq_ignore_ascii_case(nick));
+        let conversation = match index {
+            Some(index) => self.conversations.remove(index),
+            None => Conversation {
+                nick: nick.to_string(),
+                ..Default::default()
+            },
+        };
+        self.conversations.insert(0, conversation);
+        &mut self.conversations[0]
+    }
+
+    fn is_selected(&self, nick: &str) -> bool {
+        self.selected
+            .as_ref()
+            .is_some_and(|selected| selected.eq_ignore_ascii_case(nick))
+    }
+
+    fn select(&mut self, nick: &str) {
+        self.selected = Some(nick.to_lowercase());
+        if let Some(conversation) = self
+            .conversations
+            .iter_mut()
+            .find(|c| c.nick.eq_ignore_ascii_case(nick))
+        {
+            if conversation.unread_count > 0 {
+                conversation.unread_count = 0;
+                self.save();
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache
+                .set(CACHE_KEY.to_string(), self.conversations.clone())
+                .map(|_| ())
+                .unwrap_or_else(|e| error!("Failed to save whispers: {:?}", e));
+        }
+    }
+
+    fn send(&mut self, nick: &str, text: String) {
+        let command = Command::Whisper {
+            nick: nick.to_string(),
+            message: text.clone(),
+        };
+        let commands = match message_length(&command) {
+            Some(length) if length > MAX_MESSAGE_LENGTH => split_command(&command),
+            _ => vec![command],
+        };
+
+        if let Some(command_tx) = self.command_tx.as_ref() {
+            for command in commands {
+                command_tx
+                    .blocking_send(command)
+                    .expect("Failed to send command");
+            }
+        }
+        self.add_outgoing(nick, text);
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        self.show_notification(ctx);
+
+        let mut is_open = self.is_open;
+        egui::Window::new("Whispers")
+            .open(&mut is_open)
+            .default_width(400.0)
+            .default_height(300.0)
+            .show(ctx, |ui| self.show_contents(ui));
+        self.is_open = is_open;
+    }
+
+    fn show_contents(&mut self, ui: &mut Ui) {
+        if self.conversations.is_empty() {
+            ui.weak("No whispers yet. Start one from a user's card.");
+            return;
+        }
+
+        let mut selected = None;
+        let mut closed = None;
+        ui.horizontal_wrapped(|ui| {
+            for conversation in &self.conversations {
+                let label = match conversation.unread_count {
+                    0 => conversation.nick.clone(),
+                    count => format!("{} ({})", conversation.nick, count),
+                };
+                let response = ui.selectable_label(self.is_selected(&conversation.nick), label);
+                if response.clicked() {
+                    selected = Some(conversation.nick.clone());
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Close conversation").clicked() {
+                        closed = Some(conversation.nick.clone());
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+        if let Some(nick) = selected {
+            self.select(&nick);
+        }
+        if let Some(nick) = closed {
+            self.conversations
+                .retain(|c| !c.nick.eq_ignore_ascii_case(&nick));
+            self.save();
+        }
+        ui.separator();
+
+        let Some(index) = self.selected.as_ref().and_then(|nick| {
+            self.conversations
+                .iter()
+                .position(|c| c.nick.eq_ignore_ascii_case(nick))
+        }) else {
+            ui.weak("Select a conversation");
+            return;
+        };
+
+        // Messages arriving while the tab is open are read right away.
+        self.conversations[index].unread_count = 0;
+
+        let input_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y;
+        let conversation = &mut self.conversations[index];
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - input_height)
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for message in &conversation.messages {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.weak(message.sent_at.format("%H:%M").to_string())
+                            .on_hover_text(
+                                message.sent_at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                            );
+                        if message.is_outgoing {
+                            ui.strong("You");
+                        } else {
+                            ui.strong(&conversation.nick);
+                        }
+                        ui.label(&message.text);
+                    });
+                }
+            });
+
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut conversation.input)
+                .hint_text(format!("Whisper to {}", conversation.nick))
+                .desired_width(f32::INFINITY),
+        );
+        if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            let text = std::mem::take(&mut conversation.input).trim().to_string();
+            let nick = conversation.nick.clone();
+            if !text.is_empty() {
+                self.send(&nick, text);
+            }
+            response.request_focus();
+        }
+    }
+
+    fn show_notification(&mut self, ctx: &Context) {
+        let Some(notification) = self.notification.as_mut() else {
+            return;
+        };
+
+        let now = ctx.input(|i| i.time);
+        let expires_at = *notification
+            .expires_at
+            .get_or_insert(now + NOTIFICATION_SECONDS);
+        if now >= expires_at {
+            self.notification = None;
+            return;
+        }
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(expires_at - now));
+
+        let mut is_clicked = false;
+        egui::Area::new("whisper_notification")
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+            .show(ctx, |ui| {
+                let response = egui::Frame::popup(ui.style())
+                    .show(ui, |ui| {
+                        ui.set_max_width(300.0);
+                        ui.colored_label(
+                            Color32::LIGHT_BLUE,
+                            format!("Whisper from {}", notification.nick),
+                        );
+                        ui.label(&notification.text);
+                    })
+                    .response
+                    .interact(egui::Sense::click())
+                    .on_hover_text("Click to open");
+                is_clicked = response.clicked();
+            });
+
+        if is_clicked {
+            let nick = notification.nick.clone();
+            self.notification = None;
+            self.open(&nick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_unread_whispers_until_their_tab_is_opened() {
+        let mut view = WhisperView::default();
+
+        view.add_incoming("Bob", "hi".to_string(), Utc::now());
+        view.add_incoming("bob", "there".to_string(), Utc::now());
+        view.add_incoming("Alice", "hey".to_string(), Utc::now());
+
+        assert_eq!(view.unread_count(), 3);
+        assert_eq!(view.conversations[0].nick, "Alice");
+
+        view.open("BOB");
+        view.add_incoming("Bob", "again".to_string(), Utc::now());
+
+        assert_eq!(view.unread_count(), 1);
+        assert_eq!(view.conversations[0].messages.len(), 3);
+    }
+}
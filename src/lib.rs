@@ -5,6 +5,7 @@
 pub mod common;
 pub mod config;
 pub mod dgg;
+pub mod settings;
 
 #[macro_use]
 extern crate log;
@@ -35,6 +35,7 @@ fn main() -> eframe::Result<()> {
     let (emotes_tx, emotes_rx) = oneshot::channel();
 
     let config = ChatAppConfig::load();
+    let app_config = config.clone();
     let services = ChatAppServices::new(config, event_tx, command_rx, flairs_tx, emotes_tx);
 
     let tokio = tokio::runtime::Builder::new_multi_thread()
@@ -44,14 +45,22 @@ fn main() -> eframe::Result<()> {
         .build()
         .unwrap();
 
-    tokio.spawn(async move {
-        services.start().await;
-    });
-
+    // The runtime has to outlive the window, so the services are spawned through a handle.
+    let runtime = tokio.handle().clone();
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
         "Destiny.gg Chat",
         native_options,
-        Box::new(|cc| Box::new(ChatApp::new(cc, event_rx, command_tx, flairs_rx, emotes_rx))),
+        Box::new(move |cc| {
+            // Started once the window exists, so that images are downloaded for its scale.
+            let services = services.with_pixels_per_point(cc.egui_ctx.pixels_per_point());
+            runtime.spawn(async move {
+                services.start().await;
+            });
+
+            Box::new(ChatApp::new(
+                cc, app_config, event_rx, command_tx, flairs_rx, emotes_rx,
+            ))
+        }),
     )
 }
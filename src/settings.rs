@@ -0,0 +1,242 @@
+use crate::dgg::filter::FilterSettings;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The name of the file (without extension) that user settings are loaded from and saved to.
+pub const SETTINGS_FILE_NAME: &str = "settings";
+
+/// Settings that can be changed from inside the app.
+///
+/// These are read from the same sources as [crate::config::ChatAppConfig], so each section can
+/// also be written by hand in `config.toml`, and are written back to `settings.toml` on change.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatAppSettings {
+    pub display: DisplaySettings,
+    pub system_messages: SystemMessageSettings,
+    pub ignore: IgnoreSettings,
+    pub filters: FilterSettings,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl ChatAppSettings {
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+
+        debug!("Saving settings to: {}", path.display());
+        let toml = toml::to_string_pretty(self).context("Failed to serialize settings")?;
+        std::fs::write(path, toml).context("Failed to write settings")?;
+        Ok(())
+    }
+}
+
+/// How the chat is displayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// Stop animating emotes while the window isn't focused.
+    pub pause_animations_when_unfocused: bool,
+    /// How large emotes are drawn relative to their native size.
+    pub emote_scale: f32,
+    /// Show consecutive messages from the same user without repeating their nick.
+    pub group_messages: bool,
+    /// How the messages of a user are shown once they are muted or banned.
+    pub censor_style: CensorStyle,
+    /// The number of messages kept in the chat before the oldest are dropped, or 0 for no limit.
+    pub max_messages: usize,
+    /// The memory the messages may use before the oldest are dropped, in megabytes, or 0 for no
+    /// limit.
+    pub max_message_memory_mb: usize,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            pause_animations_when_unfocused: true,
+            emote_scale: 1.0,
+            group_messages: true,
+            censor_style: CensorStyle::default(),
+            max_messages: 5000,
+            max_message_memory_mb: 64,
+        }
+    }
+}
+
+/// How the earlier messages of a muted or banned user are shown, until clicked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CensorStyle {
+    /// Leave the messages as they are.
+    Off,
+    /// Cover the messages.
+    Blur,
+    #[default]
+    Strikethrough,
+    /// Replace the messages with a line saying they were censored.
+    Collapse,
+}
+
+impl CensorStyle {
+    pub const ALL: [CensorStyle; 4] = [
+        CensorStyle::Off,
+        CensorStyle::Blur,
+        CensorStyle::Strikethrough,
+        CensorStyle::Collapse,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CensorStyle::Off => "Off",
+            CensorStyle::Blur => "Blur",
+            CensorStyle::Strikethrough => "Strike through",
+            CensorStyle::Collapse => "Collapse",
+        }
+    }
+}
+
+/// The kinds of events shown as system lines between the chat messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemMessageKind {
+    Broadcast,
+    Mute,
+    Unmute,
+    Ban,
+    Unban,
+    SubOnly,
+}
+
+/// Which kinds of system lines are shown in the chat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemMessageSettings {
+    pub broadcasts: bool,
+    pub mutes: bool,
+    pub unmutes: bool,
+    pub bans: bool,
+    pub unbans: bool,
+    pub sub_only: bool,
+}
+
+impl Default for SystemMessageSettings {
+    fn default() -> Self {
+        Self {
+            broadcasts: true,
+            mutes: true,
+            unmutes: true,
+            bans: true,
+            unbans: true,
+            sub_only: true,
+        }
+    }
+}
+
+impl SystemMessageSettings {
+    pub fn shows(&self, kind: SystemMessageKind) -> bool {
+        match kind {
+            SystemMessageKind::Broadcast => self.broadcasts,
+            SystemMessageKind::Mute => self.mutes,
+            SystemMessageKind::Unmute => self.unmutes,
+            SystemMessageKind::Ban => self.bans,
+            SystemMessageKind::Unban => self.unbans,
+            SystemMessageKind::SubOnly => self.sub_only,
+        }
+    }
+}
+
+/// Users whose messages and whispers are hidden.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IgnoreSettings {
+    pub users: Vec<String>,
+    /// Also hide messages that mention an ignored user.
+    pub hide_mentions: bool,
+}
+
+impl IgnoreSettings {
+    pub fn is_ignored(&self, nick: &str) -> bool {
+        self.users.iter().any(|u| u.eq_ignore_ascii_case(nick))
+    }
+
+    /// Returns `false` if the user was already ignored.
+    pub fn ignore(&mut self, nick: &str) -> bool {
+        if self.is_ignored(nick) {
+            return false;
+        }
+
+        self.users.push(nick.to_string());
+        true
+    }
+
+    /// Returns `false` if the user wasn't ignored.
+    pub fn unignore(&mut self, nick: &str) -> bool {
+        let len = self.users.len();
+        self.users.retain(|u| !u.eq_ignore_ascii_case(nick));
+        self.users.len() != len
+    }
+
+    pub fn mentions_ignored_user(&self, text: &str) -> bool {
+        text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .any(|word| !word.is_empty() && self.is_ignored(word))
+    }
+
+    /// Whether a message from `nick` with the given text should be hidden.
+    pub fn hides(&self, nick: &str, text: &str) -> bool {
+        self.is_ignored(nick) || (self.hide_mentions && self.mentions_ignored_user(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dgg::filter::{FilterAction, FilterRule};
+
+    #[test]
+    fn settings_round_trip_through_toml() -> Result<()> {
+        let mut settings = ChatAppSettings::default();
+        settings.ignore.ignore("Destiny");
+        let mut rule = FilterRule::new("links".to_string(), FilterAction::Collapse);
+        rule.has_link = Some(true);
+        settings.filters.rules.push(rule);
+
+        let toml = toml::to_string_pretty(&settings)?;
+        let deserialized: ChatAppSettings = toml::from_str(&toml)?;
+
+        assert_eq!(deserialized.ignore, settings.ignore);
+        assert_eq!(deserialized.filters.rules[0].action, FilterAction::Collapse);
+        assert_eq!(deserialized.filters.rules[0].has_link, Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_is_case_insensitive() {
+        let mut ignore = IgnoreSettings::default();
+
+        assert!(ignore.ignore("Destiny"));
+        assert!(!ignore.ignore("destiny"));
+        assert!(ignore.is_ignored("DESTINY"));
+        assert!(ignore.unignore("destiny"));
+        assert!(!ignore.is_ignored("Destiny"));
+    }
+
+    #[test]
+    fn hides_mentions_only_when_enabled() {
+        let mut ignore = IgnoreSettings::default();
+        ignore.ignore("Destiny");
+
+        assert!(ignore.hides("destiny", "hello"));
+        assert!(!ignore.hides("someone", "hi Destiny!"));
+
+        ignore.hide_mentions = true;
+        assert!(ignore.hides("someone", "hi Destiny!"));
+        assert!(!ignore.hides("someone", "hi DestinyFan"));
+    }
+}